@@ -4,17 +4,25 @@ use nalgebra::Vector3;
 
 use crate::{generate_sphere, webgl_object::WebGLObject};
 
+/// Meshes shared by everything the renderer draws
 #[derive(Default)]
 pub struct Assets {
     meshes: HashMap<String, WebGLObject>,
 }
 
 impl Assets {
+    /// Generate every mesh
     pub fn new() -> Assets {
         let mut meshes: HashMap<String, WebGLObject> = HashMap::new();
 
         meshes.insert("Sphere".to_string(), Self::gen_sphere());
         meshes.insert("Axis".to_string(), Self::gen_axis());
+        meshes.insert(
+            "UnitLine".to_string(),
+            Self::gen_line(Vector3::zeros(), Vector3::x()),
+        );
+        meshes.insert("Ring".to_string(), Self::gen_ring());
+        meshes.insert("Arrow".to_string(), Self::gen_arrow());
 
         Assets { meshes }
     }
@@ -40,6 +48,7 @@ impl Assets {
         }
     }
 
+    /// A single line segment
     pub fn gen_line(start: Vector3<f32>, end: Vector3<f32>) -> WebGLObject {
         WebGLObject {
             vertices: vec![start.x, start.y, start.z, end.x, end.y, end.z],
@@ -48,8 +57,44 @@ impl Assets {
         }
     }
 
+    /// A line of unit length along the x axis with a head at the far end, drawn as a line strip
+    fn gen_arrow() -> WebGLObject {
+        let (back, width) = (0.85, 0.06);
+        let tip = [1., 0., 0.];
+        let mut vertices = vec![0., 0., 0.];
+        for (y, z) in [(width, 0.), (-width, 0.), (0., width), (0., -width)].iter() {
+            vertices.extend_from_slice(&tip);
+            vertices.extend_from_slice(&[back, *y, *z]);
+        }
+        vertices.extend_from_slice(&tip);
+
+        WebGLObject {
+            vertices,
+            indices: vec![],
+            normals: vec![],
+        }
+    }
+
+    /// A circle of unit radius around the x axis, drawn as a closed line strip
+    fn gen_ring() -> WebGLObject {
+        let segments = 64;
+        let vertices = (0..=segments)
+            .flat_map(|i| {
+                let angle = 2. * std::f32::consts::PI * i as f32 / segments as f32;
+                vec![0., angle.cos(), angle.sin()]
+            })
+            .collect();
+
+        WebGLObject {
+            vertices,
+            indices: vec![],
+            normals: vec![],
+        }
+    }
+
     // In a real application you would download via XHR or fetch request, but here we just
 
+    /// Look up a mesh generated by `new`
     pub fn get_mesh(&self, mesh_name: &str) -> Option<&WebGLObject> {
         self.meshes.get(mesh_name)
     }
@@ -1,3 +1,5 @@
+//! Application state and the assets used to draw it
+
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -10,6 +12,7 @@ pub use self::assets::*;
 /// Used to instantiate our application
 pub struct App {
     assets: Assets,
+    /// Everything that changes while the application runs
     pub store: Rc<RefCell<Store>>,
 }
 
@@ -24,7 +27,14 @@ impl App {
         }
     }
 
+    /// Meshes used by the renderer
     pub fn assets(&self) -> &Assets {
         &self.assets
     }
 }
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,40 @@
+//! The space the objects take up, for fitting them in the view
+
+use crate::app::store::picking::{sphere_centers, SPHERE_RADIUS};
+use crate::simulation::dipole::Object;
+use nalgebra::Vector3;
+
+/// A sphere enclosing everything drawn of some objects
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    /// Centre of the sphere
+    pub center: Vector3<f64>,
+    /// Radius of the sphere
+    pub radius: f64,
+}
+
+impl Bounds {
+    /// The sphere around every sphere the objects are drawn with, or `None` without objects.
+    /// Centred on the box around them, so it is close to the smallest but not always it.
+    pub fn of(objects: &[&dyn Object]) -> Option<Bounds> {
+        let centers: Vec<Vector3<f64>> = objects
+            .iter()
+            .flat_map(|object| sphere_centers(*object))
+            .collect();
+        let first = *centers.first()?;
+
+        let (min, max) = centers.iter().fold((first, first), |(min, max), center| {
+            (min.inf(center), max.sup(center))
+        });
+        let center = (min + max) / 2.;
+        let radius = centers
+            .iter()
+            .map(|point| (point - center).norm())
+            .fold(0., f64::max);
+
+        Some(Bounds {
+            center,
+            radius: radius + SPHERE_RADIUS,
+        })
+    }
+}
@@ -1,11 +1,57 @@
-use nalgebra::{Isometry3, Perspective3, Point3, Vector3};
+use super::{Preset, Projection};
+use nalgebra::{Isometry3, Orthographic3, Perspective3, Point3, Vector3};
 use std::f32::consts::PI;
 
+/// Room left around a framed sphere, as a multiple of its radius
+const FRAME_MARGIN: f32 = 1.2;
+
+/// Closest the camera can get to its target. Objects are drawn the same size in any scene, so
+/// this doesn't change with it.
+const MIN_DISTANCE: f32 = 1.;
+
+/// Farthest the camera can get from its target, as a multiple of the distance that frames the
+/// scene
+const MAX_ZOOM: f32 = 3.;
+
+/// How far past the scene the clip planes are put, as a multiple of its radius, leaving room for
+/// handles and arrows sticking out of it
+const CLIP_MARGIN: f32 = 2.;
+
+/// Smallest distance to the near plane, as a fraction of the distance to the far plane, so that
+/// depth doesn't lose all its precision
+const MIN_NEAR: f32 = 0.001;
+
+/// Seconds taken to swing round to a preset view
+const TRANSITION_TIME: f32 = 0.5;
+
+/// Steepest the camera can look down or up, just short of straight, where the view would have no
+/// up
+pub(super) const MAX_PITCH: f32 = PI / 2.000001;
+
+/// A swing of the camera round its target from one angle to another
+struct Transition {
+    /// Yaw and pitch it started from
+    from: (f32, f32),
+    /// Yaw and pitch it ends at
+    to: (f32, f32),
+    /// Seconds since it started
+    elapsed: f32,
+}
+
 pub struct Camera {
     projection: Perspective3<f32>,
+    /// Whether the view is drawn in perspective or flat
+    mode: Projection,
     left_right_radians: f32,
     up_down_radians: f32,
     orbit_radius: f32,
+    /// The point the camera looks at and orbits about
+    target: Point3<f32>,
+    /// Closest and farthest the camera can zoom to its target
+    zoom_limits: (f32, f32),
+    /// Centre and radius of a sphere around the scene, which the clip planes are kept around
+    scene: Option<(Point3<f32>, f32)>,
+    transition: Option<Transition>,
 }
 
 impl Camera {
@@ -13,21 +59,20 @@ impl Camera {
         let fovy = PI / 3.0;
 
         Camera {
-            projection: Perspective3::new(fovy, 1.0, 0.1, 50.0),
+            projection: Perspective3::new(1.0, fovy, 0.1, 50.0),
+            mode: Projection::Perspective,
             left_right_radians: 0.0f32.to_radians(),
             up_down_radians: 00.0f32.to_radians(),
             orbit_radius: 30.,
+            target: Point3::origin(),
+            zoom_limits: (5., 30.),
+            scene: None,
+            transition: None,
         }
     }
 
     pub fn view(&self) -> [f32; 16] {
-        let eye = self.get_eye_pos();
-
-        let target = Point3::new(0.0, 0.0, 0.0);
-
-        let view = Isometry3::look_at_rh(&eye, &target, &Vector3::y());
-
-        let view = view.to_homogeneous();
+        let view = self.look_at().to_homogeneous();
 
         let mut view_array = [0.; 16];
         view_array.copy_from_slice(view.as_slice());
@@ -38,8 +83,8 @@ impl Camera {
     pub fn view_flipped_y(&self) -> [f32; 16] {
         let mut eye = self.get_eye_pos();
         eye.y *= -1.0;
-
-        let target = Point3::new(0.0, 0.0, 0.0);
+        let mut target = self.target;
+        target.y *= -1.0;
 
         let view = Isometry3::look_at_rh(&eye, &target, &Vector3::y());
 
@@ -51,46 +96,245 @@ impl Camera {
         view_array
     }
 
-    pub fn get_eye_pos(&self) -> Point3<f32> {
+    /// The transform from the scene into the space of the camera
+    fn look_at(&self) -> Isometry3<f32> {
+        Isometry3::look_at_rh(&self.get_eye_pos(), &self.target, &Vector3::y())
+    }
+
+    /// The ray from the eye through a point of the view, given in normalized device coordinates
+    /// running from -1 to 1 left to right and bottom to top. Returns its origin and direction.
+    pub fn ray(&self, x: f32, y: f32) -> (Point3<f32>, Vector3<f32>) {
+        let view = self.look_at();
+
+        let near = self.unproject_point(&Point3::new(x, y, -1.));
+        let far = self.unproject_point(&Point3::new(x, y, 1.));
+        let near = view.inverse_transform_point(&near);
+        let far = view.inverse_transform_point(&far);
+
+        (near, (far - near).normalize())
+    }
+
+    /// Where a point is drawn, in normalized device coordinates running from -1 to 1 left to
+    /// right and bottom to top, if it is in front of the camera
+    pub fn project(&self, point: Point3<f32>) -> Option<(f32, f32)> {
+        let point = self.look_at().transform_point(&point);
+        if point.z >= 0. {
+            return None;
+        }
+        let point = match self.mode {
+            Projection::Perspective => self.projection.project_point(&point),
+            Projection::Orthographic => self.orthographic().project_point(&point),
+        };
+        Some((point.x, point.y))
+    }
+
+    /// The point in the space of the camera drawn at normalized device coordinates
+    fn unproject_point(&self, point: &Point3<f32>) -> Point3<f32> {
+        match self.mode {
+            Projection::Perspective => self.projection.unproject_point(point),
+            Projection::Orthographic => self.orthographic().unproject_point(point),
+        }
+    }
+
+    /// The flat projection showing as much of the plane through the target as the perspective
+    /// one does, so that switching between them keeps the target the same size
+    fn orthographic(&self) -> Orthographic3<f32> {
+        let top = self.orbit_radius * (self.projection.fovy() / 2.).tan();
+        let right = top * self.projection.aspect();
+        let (near, far) = self.clip_planes();
+        Orthographic3::new(-right, right, -top, top, near, far)
+    }
+
+    /// Fit the view to a canvas `aspect` times as wide as it is tall
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.projection.set_aspect(aspect);
+    }
+
+    /// Whether the view is drawn in perspective or flat
+    pub fn projection_mode(&self) -> Projection {
+        self.mode
+    }
+
+    /// Draw the view in perspective or flat
+    pub fn set_projection_mode(&mut self, mode: Projection) {
+        self.mode = mode;
+    }
+
+    /// Swing round the target to look at it from `preset`, over a moment as frames are animated
+    pub fn look_from(&mut self, preset: Preset) {
+        let from = (self.left_right_radians, self.up_down_radians);
+        let (yaw, pitch) = preset.angles();
+        // Go the short way round
+        let turn = (yaw - from.0 + PI).rem_euclid(2. * PI) - PI;
+        self.transition = Some(Transition {
+            from,
+            to: (from.0 + turn, pitch),
+            elapsed: 0.,
+        });
+    }
+
+    /// Move along any swing to a preset view by `dt` seconds
+    pub fn animate(&mut self, dt: f32) {
+        let transition = match &mut self.transition {
+            Some(transition) => transition,
+            None => return,
+        };
+        transition.elapsed += dt;
+        let t = (transition.elapsed / TRANSITION_TIME).min(1.);
+        let (from, to) = (transition.from, transition.to);
+        if t >= 1. {
+            self.transition = None;
+        }
+
+        // Ease in and out
+        let t = t * t * (3. - 2. * t);
+        self.left_right_radians = from.0 + (to.0 - from.0) * t;
+        self.up_down_radians = from.1 + (to.1 - from.1) * t;
+        self.clamp_pitch();
+        self.clip();
+    }
+
+    /// Whether the camera is swinging round to a preset view
+    pub fn animating(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Unit vector in the direction the camera looks
+    pub fn forward(&self) -> Vector3<f32> {
+        -self.backward()
+    }
+
+    /// Unit vector from the target to the eye, worked out from the angles rather than the eye so
+    /// that it stays exact looking straight down
+    fn backward(&self) -> Vector3<f32> {
         let yaw = self.left_right_radians;
         let pitch = self.up_down_radians;
 
-        let eye_x = self.orbit_radius * yaw.sin() * pitch.cos();
-        let eye_y = self.orbit_radius * pitch.sin();
-        let eye_z = self.orbit_radius * yaw.cos() * pitch.cos();
+        Vector3::new(
+            yaw.sin() * pitch.cos(),
+            pitch.sin(),
+            yaw.cos() * pitch.cos(),
+        )
+    }
+
+    pub fn get_eye_pos(&self) -> Point3<f32> {
+        self.target + self.backward() * self.orbit_radius
+    }
+
+    /// The point the camera looks at and orbits about
+    pub fn target(&self) -> Point3<f32> {
+        self.target
+    }
+
+    /// Look at and orbit about `target`, keeping the same angle and distance
+    pub fn set_target(&mut self, target: Point3<f32>) {
+        self.target = target;
+        self.clip();
+    }
+
+    /// Distance from the eye to the target
+    pub fn orbit_radius(&self) -> f32 {
+        self.orbit_radius
+    }
+
+    /// Distances from the eye to the near and far clip planes
+    pub fn clip_planes(&self) -> (f32, f32) {
+        (self.projection.znear(), self.projection.zfar())
+    }
+
+    /// Keep the scene, which lies within `radius` of `center`, in reach of the zoom and between
+    /// the clip planes
+    pub fn set_scene(&mut self, center: Point3<f32>, radius: f32) {
+        let max = (self.framing_distance(radius) * MAX_ZOOM).max(MIN_DISTANCE);
+        self.zoom_limits = (MIN_DISTANCE, max);
+        // The eye may be further out than the new limits allow
+        self.orbit_radius = self.orbit_radius.clamp(MIN_DISTANCE, max);
+        self.scene = Some((center, radius));
+        self.clip();
+    }
+
+    /// Look at `center` from just far enough away to see everything within `radius` of it
+    pub fn frame(&mut self, center: Point3<f32>, radius: f32) {
+        self.target = center;
+        let (min, max) = self.zoom_limits;
+        self.orbit_radius = self.framing_distance(radius).clamp(min, max);
+        self.clip();
+    }
 
-        Point3::new(eye_x, eye_y, eye_z)
+    /// How far from the centre of a sphere of `radius` the eye has to be to fit it in the view,
+    /// with a margin
+    fn framing_distance(&self, radius: f32) -> f32 {
+        let half_fovy = self.projection.fovy() / 2.;
+        let half_fovx = (half_fovy.tan() * self.projection.aspect()).atan();
+        let half_fov = half_fovy.min(half_fovx);
+        match self.mode {
+            Projection::Perspective => radius * FRAME_MARGIN / half_fov.sin(),
+            // The flat view shows as much as the perspective one does at the target
+            Projection::Orthographic => radius * FRAME_MARGIN / half_fov.tan(),
+        }
+    }
+
+    /// Move the clip planes to just around the scene as seen from where the eye is now
+    fn clip(&mut self) {
+        let (center, radius) = match self.scene {
+            Some(scene) => scene,
+            None => return,
+        };
+        let distance = (center - self.get_eye_pos()).norm();
+        let far = distance + radius * CLIP_MARGIN;
+        let near = (distance - radius * CLIP_MARGIN).max(far * MIN_NEAR);
+        self.projection.set_znear_and_zfar(near, far);
     }
+
+    /// Move the target across the view so that the scene follows a drag of `dx` pixels right
+    /// and `dy` pixels down, in a view `width` by `height` pixels
+    pub fn pan(&mut self, dx: f32, dy: f32, width: f32, height: f32) {
+        // How far a pixel spans at the distance of the target, across and up
+        let span = 2. * self.orbit_radius * (self.projection.fovy() / 2.).tan();
+        let (per_x, per_y) = (span * self.projection.aspect() / width, span / height);
+
+        let yaw = self.left_right_radians;
+        let right = Vector3::new(yaw.cos(), 0., -yaw.sin());
+        let up = right.cross(&self.forward());
+
+        self.target += up * dy * per_y - right * dx * per_x;
+        self.clip();
+    }
+
     pub fn projection(&self) -> [f32; 16] {
+        let matrix = match self.mode {
+            Projection::Perspective => *self.projection.as_matrix(),
+            Projection::Orthographic => *self.orthographic().as_matrix(),
+        };
         let mut perspective_array = [0.; 16];
-        perspective_array.copy_from_slice(self.projection.as_matrix().as_slice());
+        perspective_array.copy_from_slice(matrix.as_slice());
 
         perspective_array
     }
 
     pub fn orbit_left_right(&mut self, delta: f32) {
+        self.transition = None;
         self.left_right_radians += delta;
+        self.clip();
     }
 
     pub fn orbit_up_down(&mut self, delta: f32) {
+        self.transition = None;
         self.up_down_radians += delta;
+        self.clamp_pitch();
+        self.clip();
+    }
 
-        // Make sure:
-        // 0.1 <= radians <= PI / 2.1
-        // in order to restrict the camera's up/down orbit motion
-
-        if self.up_down_radians - (PI / 2.0) > 0. {
-            self.up_down_radians = PI / 2.000001;
-        }
-
-        if self.up_down_radians + (PI / 2.0) < 0. {
-            self.up_down_radians = -PI / 2.00001;
-        }
+    /// Keep the eye from going over the top or under the bottom, where the view would have no up
+    fn clamp_pitch(&mut self) {
+        self.up_down_radians = self.up_down_radians.clamp(-MAX_PITCH, MAX_PITCH);
     }
 
     pub fn zoom(&mut self, zoom: f32) {
         self.orbit_radius += zoom;
 
-        self.orbit_radius = self.orbit_radius.clamp(5., 30.);
+        let (min, max) = self.zoom_limits;
+        self.orbit_radius = self.orbit_radius.clamp(min, max);
+        self.clip();
     }
 }
@@ -0,0 +1,50 @@
+//! Things that happen in the simulation that the page embedding it may want to react to
+
+use crate::simulation::diagnostics::{DriftReport, Observables};
+
+/// Something that happened while a message was applied to the `State`
+#[derive(Clone, Copy, Debug)]
+pub enum SimulationEvent {
+    /// The simulation advanced to `time`
+    Step {
+        /// Simulated time after the step
+        time: f64,
+        /// The observables measured after the step
+        observables: Observables,
+    },
+    /// Two objects came closer than the close approach distance. Fired again only once they
+    /// have moved apart.
+    CloseApproach {
+        /// Simulated time of the approach
+        time: f64,
+        /// Index of the first object
+        first: usize,
+        /// Index of the second object
+        second: usize,
+        /// Distance between their centres
+        distance: f64,
+    },
+    /// A conserved quantity drifted further than the drift threshold for the first time since
+    /// the last reset
+    DriftExceeded {
+        /// The drift that crossed the threshold
+        drift: DriftReport,
+    },
+    /// The simulation was reset or replaced, so the clock starts over
+    Reset,
+}
+
+impl SimulationEvent {
+    /// The name of every kind of event
+    pub const NAMES: [&'static str; 4] = ["step", "closeapproach", "driftexceeded", "reset"];
+
+    /// Name the event is subscribed to by
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimulationEvent::Step { .. } => "step",
+            SimulationEvent::CloseApproach { .. } => "closeapproach",
+            SimulationEvent::DriftExceeded { .. } => "driftexceeded",
+            SimulationEvent::Reset => "reset",
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! What fingers on the canvas are doing. One finger works like the mouse, two pinch, pan and
+//! twist the view.
+
+/// Two fingers on the canvas, in pixels from its top left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pinch {
+    first: (f32, f32),
+    second: (f32, f32),
+}
+
+impl Pinch {
+    /// The first two of `touches`, if there are two
+    pub fn of(touches: &[(i32, i32)]) -> Option<Pinch> {
+        let point = |(x, y): (i32, i32)| (x as f32, y as f32);
+        match touches {
+            [first, second, ..] => Some(Pinch {
+                first: point(*first),
+                second: point(*second),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Halfway between the fingers
+    pub fn midpoint(&self) -> (f32, f32) {
+        (
+            (self.first.0 + self.second.0) / 2.,
+            (self.first.1 + self.second.1) / 2.,
+        )
+    }
+
+    /// Distance between the fingers
+    pub fn spread(&self) -> f32 {
+        let (dx, dy) = (self.second.0 - self.first.0, self.second.1 - self.first.1);
+        dx.hypot(dy)
+    }
+
+    /// Angle of the line from the first finger to the second, clockwise on the canvas
+    pub fn angle(&self) -> f32 {
+        (self.second.1 - self.first.1).atan2(self.second.0 - self.first.0)
+    }
+}
+
+/// What the fingers on the canvas are doing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// One finger, pressing like the mouse does, to tap, drag or orbit
+    Press,
+    /// Two fingers, where they were last
+    Pinch(Pinch),
+    /// Fingers left down after a pinch, which do nothing until they are all lifted
+    Finished,
+}
@@ -0,0 +1,185 @@
+//! The handles drawn around the selected object for dragging it about and turning it
+
+use crate::app::store::slingshot::{Motion, ARROW_SCALE};
+use crate::simulation::dipole::Edit;
+use nalgebra::{Point3, Rotation3, Unit, Vector3};
+
+/// The x, y and z axes the handles lie along
+pub const AXES: [[f64; 3]; 3] = [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+
+/// Length of the axis handles per unit of distance from the eye, so that the gizmo looks the same
+/// size however far away it is
+const SIZE: f64 = 0.08;
+
+/// Radius of the rings, as a fraction of the length of the axis handles
+const RING_RADIUS: f64 = 0.75;
+
+/// How close a ray has to pass to a handle to grab it, as a fraction of the length of the axis
+/// handles
+const TOLERANCE: f64 = 0.08;
+
+/// A part of the gizmo that can be dragged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handle {
+    /// Moves the object along the x, y or z axis
+    Axis(usize),
+    /// Turns a dipole about the x, y or z axis
+    Ring(usize),
+    /// Moves the object in the plane facing the camera. Grabbed by the object itself.
+    Plane,
+    /// Sets a motion of the object to the arrow pulled out of it, in the plane facing the camera
+    Arrow(Motion),
+}
+
+/// The handles around an object, sized for the distance they are seen from
+#[derive(Clone, Copy, Debug)]
+pub struct Gizmo {
+    /// Where the handles meet
+    pub center: Vector3<f64>,
+    /// Length of the axis handles
+    pub size: f64,
+    /// Whether there are rings, which only dipoles have
+    pub rings: bool,
+}
+
+impl Gizmo {
+    /// The gizmo around an object at `center` seen from `eye`
+    pub fn new(eye: Point3<f64>, center: Vector3<f64>, rings: bool) -> Gizmo {
+        Gizmo {
+            center,
+            size: SIZE * (center - eye.coords).norm(),
+            rings,
+        }
+    }
+
+    /// Radius of the rings
+    pub fn ring_radius(&self) -> f64 {
+        self.size * RING_RADIUS
+    }
+
+    /// The nearest axis handle or ring the ray with unit `direction` passes close to, and the
+    /// point on it that was grabbed
+    pub fn hit(
+        &self,
+        origin: Point3<f64>,
+        direction: Vector3<f64>,
+    ) -> Option<(Handle, Vector3<f64>)> {
+        let tolerance = self.size * TOLERANCE;
+        let mut nearest: Option<(Handle, Vector3<f64>, f64)> = None;
+        let mut consider = |handle, point: Vector3<f64>, distance: f64| {
+            if nearest.is_none_or(|(_, _, nearest)| distance < nearest) {
+                nearest = Some((handle, point, distance));
+            }
+        };
+
+        for (i, axis) in AXES.iter().enumerate() {
+            let axis = Vector3::from(*axis);
+            if let Some((along, distance)) = closest_to_ray(origin, direction, self.center, axis) {
+                let point = self.center + axis * along;
+                let miss = (origin.coords + direction * distance - point).norm();
+                if (0. ..=self.size).contains(&along) && distance > 0. && miss < tolerance {
+                    consider(Handle::Axis(i), point, distance);
+                }
+            }
+
+            if !self.rings {
+                continue;
+            }
+            if let Some(point) = ray_plane(origin, direction, self.center, axis) {
+                let radius = (point - self.center).norm();
+                if (radius - self.ring_radius()).abs() < tolerance {
+                    consider(Handle::Ring(i), point, (point - origin.coords).norm());
+                }
+            }
+        }
+
+        nearest.map(|(handle, point, _)| (handle, point))
+    }
+}
+
+/// An object being dragged by one of the handles of its gizmo, or by an arrow pulled out of it
+#[derive(Clone, Copy, Debug)]
+pub struct Drag {
+    /// Index of the object
+    pub index: usize,
+    /// The handle being dragged
+    pub handle: Handle,
+    /// Where the handle was grabbed
+    pub grab: Vector3<f64>,
+    /// Position of the object when it was grabbed
+    pub position: Vector3<f64>,
+    /// Orientation of the object when it was grabbed
+    pub orientation: Vector3<f64>,
+    /// Whether the simulation was paused before the drag paused it
+    pub was_paused: bool,
+}
+
+impl Drag {
+    /// The change to the object that follows the handle to the ray with unit `direction`.
+    /// `facing` is the direction the camera looks in, which the plane handle moves across.
+    pub fn follow(
+        &self,
+        origin: Point3<f64>,
+        direction: Vector3<f64>,
+        facing: Vector3<f64>,
+    ) -> Option<Edit> {
+        match self.handle {
+            Handle::Axis(i) => {
+                let axis = Vector3::from(AXES[i]);
+                let (along, _) = closest_to_ray(origin, direction, self.grab, axis)?;
+                Some(Edit::Position(self.position + axis * along))
+            }
+            Handle::Plane => {
+                let point = ray_plane(origin, direction, self.grab, facing)?;
+                Some(Edit::Position(self.position + point - self.grab))
+            }
+            Handle::Arrow(motion) => {
+                let point = ray_plane(origin, direction, self.position, facing)?;
+                Some(motion.edit((point - self.position) / ARROW_SCALE))
+            }
+            Handle::Ring(i) => {
+                let axis = Vector3::from(AXES[i]);
+                let point = ray_plane(origin, direction, self.position, axis)?;
+                let (from, to) = (self.grab - self.position, point - self.position);
+                let angle = axis.dot(&from.cross(&to)).atan2(from.dot(&to));
+                let rotation = Rotation3::from_axis_angle(&Unit::new_normalize(axis), angle);
+                Some(Edit::Orientation(rotation * self.orientation))
+            }
+        }
+    }
+}
+
+/// Where the line through `point` along unit `axis` passes closest to the ray with unit
+/// `direction`, as the distance along the line and the distance along the ray. `None` if they
+/// are parallel.
+fn closest_to_ray(
+    origin: Point3<f64>,
+    direction: Vector3<f64>,
+    point: Vector3<f64>,
+    axis: Vector3<f64>,
+) -> Option<(f64, f64)> {
+    let w = origin.coords - point;
+    let b = direction.dot(&axis);
+    let denominator = 1. - b * b;
+    if denominator < 1e-9 {
+        return None;
+    }
+    let (d, e) = (direction.dot(&w), axis.dot(&w));
+    Some(((e - b * d) / denominator, (b * e - d) / denominator))
+}
+
+/// Where the ray with unit `direction` meets the plane through `point` with `normal`, if it does
+/// so in front of its origin
+pub fn ray_plane(
+    origin: Point3<f64>,
+    direction: Vector3<f64>,
+    point: Vector3<f64>,
+    normal: Vector3<f64>,
+) -> Option<Vector3<f64>> {
+    let distance = normal.dot(&(point - origin.coords)) / normal.dot(&direction);
+    if distance.is_finite() && distance > 0. {
+        Some(origin.coords + direction * distance)
+    } else {
+        None
+    }
+}
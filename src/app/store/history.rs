@@ -0,0 +1,104 @@
+//! Rolling record of observables over simulated time
+
+use crate::simulation::diagnostics::Observables;
+use std::collections::VecDeque;
+
+/// A quantity that can be plotted against simulated time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Series {
+    /// Total energy
+    Energy,
+    /// x component of the total momentum
+    MomentumX,
+    /// y component of the total momentum
+    MomentumY,
+    /// z component of the total momentum
+    MomentumZ,
+    /// Magnitude of the total angular momentum
+    AngularMomentum,
+    /// Kinetic temperature
+    Temperature,
+}
+
+impl Series {
+    /// Every series, in the order they are offered
+    pub const ALL: [Series; 6] = [
+        Series::Energy,
+        Series::MomentumX,
+        Series::MomentumY,
+        Series::MomentumZ,
+        Series::AngularMomentum,
+        Series::Temperature,
+    ];
+
+    /// Human readable name
+    pub fn label(&self) -> &'static str {
+        match self {
+            Series::Energy => "Energy",
+            Series::MomentumX => "Momentum x",
+            Series::MomentumY => "Momentum y",
+            Series::MomentumZ => "Momentum z",
+            Series::AngularMomentum => "|Angular momentum|",
+            Series::Temperature => "Temperature",
+        }
+    }
+
+    /// Pick this series out of a measurement
+    pub fn value(&self, observables: &Observables) -> f64 {
+        match self {
+            Series::Energy => observables.energy,
+            Series::MomentumX => observables.momentum.x,
+            Series::MomentumY => observables.momentum.y,
+            Series::MomentumZ => observables.momentum.z,
+            Series::AngularMomentum => observables.angular_momentum.norm(),
+            Series::Temperature => observables.temperature,
+        }
+    }
+}
+
+/// The observables of the last `window` seconds of simulated time
+pub struct History {
+    window: f64,
+    samples: VecDeque<(f64, Observables)>,
+}
+
+impl History {
+    /// An empty history covering `window` seconds
+    pub fn new(window: f64) -> History {
+        History {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Seconds of simulated time kept
+    pub fn window(&self) -> f64 {
+        self.window
+    }
+
+    /// Change how many seconds of simulated time are kept
+    pub fn set_window(&mut self, window: f64) {
+        self.window = window;
+    }
+
+    /// Forget every sample
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Add a sample and drop those older than the window
+    pub fn record(&mut self, time: f64, observables: Observables) {
+        self.samples.push_back((time, observables));
+        while let Some((oldest, _)) = self.samples.front() {
+            if time - oldest <= self.window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples of simulated time and observables, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &(f64, Observables)> {
+        self.samples.iter()
+    }
+}
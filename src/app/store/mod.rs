@@ -1,6 +1,21 @@
-use crate::simulation::dipole::ChargeSimulation;
+//! The state of the application and the messages that change it
+
+use crate::logging;
+use crate::simulation::batch;
+use crate::simulation::binary_trajectory::TrajectoryReader;
+use crate::simulation::comparison::{Divergence, Run, Settings};
+use crate::simulation::diagnostics::ConservationDiagnostics;
 use crate::simulation::dipole::DipoleSimulation;
+use crate::simulation::dipole::Edit;
+use crate::simulation::dipole::Objects;
 use crate::simulation::dipole::Simulatable;
+use crate::simulation::integrator::Integrator;
+use crate::simulation::playback::Playback;
+use crate::simulation::scenario::{ObjectSpec, Scenario};
+use crate::simulation::trajectory::Trajectory;
+use nalgebra::{Point3, Vector3};
+use std::f32::consts::PI;
+use std::io::Cursor;
 use std::ops::Deref;
 
 mod mouse;
@@ -9,81 +24,488 @@ use self::mouse::*;
 mod camera;
 use self::camera::*;
 
+mod history;
+pub use self::history::*;
+
+mod events;
+pub use self::events::*;
+
+mod stepper;
+pub use self::stepper::*;
+
+mod picking;
+pub use self::picking::*;
+
+mod gizmo;
+pub use self::gizmo::*;
+
+mod slingshot;
+pub use self::slingshot::*;
+
+mod bounds;
+pub use self::bounds::*;
+
+mod gesture;
+pub use self::gesture::*;
+
+/// Relative drift of a conserved quantity above which the simulation is flagged as unreliable
+const DRIFT_THRESHOLD: f64 = 0.01;
+
+/// Seconds of simulated time per physics step
+const PHYSICS_DT: f64 = 1. / 120.;
+
+/// Most physics steps taken for one frame. Time beyond this is dropped.
+const MAX_STEPS_PER_FRAME: u32 = 64;
+
+/// Distance between two objects below which a close approach is reported
+const CLOSE_APPROACH_DISTANCE: f64 = 1.;
+
+/// How far along the x axis a duplicate is placed from the original, so the two don't overlap
+const DUPLICATE_SPACING: f64 = 1.5;
+
+/// Owns the application state. All changes go through `msg`.
 pub struct Store {
+    /// The current state
     pub state: StateWrapper,
 }
 
 impl Store {
+    /// The initial state of the application
     pub fn new() -> Store {
         Store {
             state: StateWrapper(State::new()),
         }
     }
 
+    /// Apply a message to the state
     pub fn msg(&mut self, msg: &Msg) {
         self.state.msg(msg);
     }
+
+    /// The events raised since this was last called, oldest first. They pile up until taken.
+    pub fn take_events(&mut self) -> Vec<SimulationEvent> {
+        std::mem::take(&mut self.state.0.events)
+    }
 }
 
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The simulation together with everything about how it is being viewed and controlled
 pub struct State {
+    /// Time elapsed since the application started, in milliseconds
     clock: f32,
     camera: Camera,
     mouse: Mouse,
     pub(crate) simulation: Box<dyn Simulatable>,
     show_scenery: bool,
     time_scale: f32,
+    paused: bool,
+    integrator: Integrator,
+    sim_time: f64,
+    record_trajectory: bool,
+    trajectory: Trajectory,
+    diagnostics: ConservationDiagnostics,
+    history: History,
+    chart_series: Vec<Series>,
+    close_approach_distance: f64,
+    /// Whether two objects are currently closer than the close approach distance
+    approaching: bool,
+    events: Vec<SimulationEvent>,
+    comparison: Option<Run>,
+    compare_view: CompareView,
+    stepper: FixedStep,
+    previous: Poses,
+    comparison_previous: Poses,
+    canvas_size: (u32, u32),
+    selected: Option<usize>,
+    tool: Tool,
+    /// Why the last object couldn't be added, until one is or the simulation restarts
+    add_error: Option<String>,
+    drag: Option<Drag>,
+    follow: Follow,
+    gesture: Option<Gesture>,
+    /// The simulation and comparison put aside while a recording plays
+    live: Option<(Box<dyn Simulatable>, Option<Run>)>,
 }
 
 impl State {
     fn new() -> State {
-        State {
-            /// Time elapsed since the application started, in milliseconds
+        let simulation: Box<dyn Simulatable> = Box::new(DipoleSimulation::new(1., 1., 1., 1.));
+        // let simulation: Box<dyn Simulatable> = Box::new(ChargeSimulation::new());
+        let diagnostics = ConservationDiagnostics::new(simulation.as_ref(), DRIFT_THRESHOLD);
+
+        let mut state = State {
             clock: 0.,
             camera: Camera::new(),
             mouse: Mouse::default(),
-            simulation: Box::new(DipoleSimulation::new(1., 1., 1., 1.)),
-            // simulation: Box::new(ChargeSimulation::new()),
+            simulation,
             show_scenery: true,
             time_scale: 1.,
-        }
+            paused: false,
+            integrator: Integrator::default(),
+            sim_time: 0.,
+            record_trajectory: false,
+            trajectory: Trajectory::new(1),
+            diagnostics,
+            history: History::new(10.),
+            chart_series: vec![Series::Energy],
+            close_approach_distance: CLOSE_APPROACH_DISTANCE,
+            approaching: false,
+            events: vec![],
+            comparison: None,
+            compare_view: CompareView::Split,
+            stepper: FixedStep::new(PHYSICS_DT, MAX_STEPS_PER_FRAME),
+            previous: Poses::default(),
+            comparison_previous: Poses::default(),
+            canvas_size: (512, 512),
+            selected: None,
+            tool: Tool::Select,
+            add_error: None,
+            drag: None,
+            follow: Follow::Free,
+            gesture: None,
+            live: None,
+        };
+        state.fit_scene();
+        state
     }
 
+    /// The camera the scene is viewed through
     pub fn camera(&self) -> &Camera {
         &self.camera
     }
 
+    /// The simulation being run
+    pub fn simulation(&self) -> &dyn Simulatable {
+        self.simulation.as_ref()
+    }
+
     /// The current time in milliseconds
     pub fn clock(&self) -> f32 {
         self.clock
     }
 
+    /// Whether the scene is drawn at all
     pub fn show_scenery(&self) -> bool {
         self.show_scenery
     }
 
+    /// Seconds of simulated time per second of real time
     pub fn time_scale(&self) -> f32 {
         self.time_scale
     }
 
+    /// Whether the simulation is held still
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The integrator the simulation is stepped with
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    /// Simulated time elapsed since the last reset
+    pub fn sim_time(&self) -> f64 {
+        self.sim_time
+    }
+
+    /// The frames recorded while trajectory recording is on
+    pub fn trajectory(&self) -> &Trajectory {
+        &self.trajectory
+    }
+
+    /// Drift of the conserved quantities since the last reset
+    pub fn diagnostics(&self) -> &ConservationDiagnostics {
+        &self.diagnostics
+    }
+
+    /// Recent observables, for charting
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// The series currently shown on the chart
+    pub fn chart_series(&self) -> &[Series] {
+        &self.chart_series
+    }
+
+    /// Distance between two objects below which a close approach is reported
+    pub fn close_approach_distance(&self) -> f64 {
+        self.close_approach_distance
+    }
+
+    /// Seconds of simulated time per physics step
+    pub fn physics_dt(&self) -> f64 {
+        self.stepper.dt()
+    }
+
+    /// How far between the last two physics steps the current frame falls, from 0 to 1
+    pub fn render_alpha(&self) -> f64 {
+        self.stepper.alpha()
+    }
+
+    /// Where the objects of the simulation were before the last physics step
+    pub fn previous_poses(&self) -> &Poses {
+        &self.previous
+    }
+
+    /// Where the objects of the comparison were before the last physics step
+    pub fn comparison_previous_poses(&self) -> &Poses {
+        &self.comparison_previous
+    }
+
+    /// Width and height of the canvas in pixels
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.canvas_size
+    }
+
+    /// The index of the selected object
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// The handles around the selected object, unless the tool in use has its own
+    pub fn gizmo(&self) -> Option<Gizmo> {
+        if let Tool::Slingshot(_) = self.tool {
+            return None;
+        }
+        let object = *self.simulation.get_objects().get(self.selected?)?;
+        Some(Gizmo::new(
+            self.camera.get_eye_pos().cast(),
+            object.get_pos(),
+            object.get_type() == Objects::Dipole,
+        ))
+    }
+
+    /// The arrows showing the motion the slingshot tool sets, for every object it applies to
+    pub fn arrows(&self) -> Vec<Arrow> {
+        let motion = match self.tool {
+            Tool::Slingshot(motion) => motion,
+            _ => return vec![],
+        };
+        self.simulation
+            .get_objects()
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| motion.applies_to(object.get_type()))
+            .map(|(index, object)| Arrow {
+                index,
+                motion,
+                start: object.get_pos(),
+                value: motion.of(*object),
+            })
+            .collect()
+    }
+
+    /// Where a point of the scene is drawn on the canvas, in pixels from the top left, if it is in
+    /// front of the camera. In a split view this is in the half showing the simulation.
+    pub fn canvas_position(&self, point: Vector3<f64>) -> Option<(f32, f32)> {
+        let (x, y) = self.camera.project(Point3::from(point.cast()))?;
+        let (width, height) = (self.canvas_size.0 as f32, self.canvas_size.1 as f32);
+        let (left, top, width, height) = match (&self.comparison, self.compare_view) {
+            (Some(_), CompareView::Split) => (0., height / 4., width / 2., height / 2.),
+            _ => (0., 0., width, height),
+        };
+        Some((left + (x + 1.) / 2. * width, top + (1. - y) / 2. * height))
+    }
+
+    /// The handle of the gizmo being dragged, if any
+    pub fn dragging(&self) -> Option<Handle> {
+        self.drag.map(|drag| drag.handle)
+    }
+
+    /// Whether a recording is playing in place of the simulation
+    pub fn playing_back(&self) -> bool {
+        self.live.is_some()
+    }
+
+    /// What clicking on the canvas does
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    /// Whether an object of `kind` can be added, and why not if it can't. An empty simulation
+    /// takes either kind.
+    pub fn can_add(&self, kind: Objects) -> Result<(), String> {
+        if self.object_count() == 0 {
+            return Ok(());
+        }
+        self.simulation.accepts(kind)
+    }
+
+    /// Why the last object couldn't be added, until one is or the simulation restarts
+    pub fn add_error(&self) -> Option<&str> {
+        self.add_error.as_deref()
+    }
+
+    /// The sphere around everything drawn of the simulation and the comparison, if there is
+    /// anything
+    pub fn scene_bounds(&self) -> Option<Bounds> {
+        let mut objects = self.simulation.get_objects();
+        if let Some(comparison) = &self.comparison {
+            objects.extend(comparison.simulation().get_objects());
+        }
+        Bounds::of(&objects)
+    }
+
+    /// What the fingers on the canvas are doing, if any are on it
+    pub fn gesture(&self) -> Option<Gesture> {
+        self.gesture
+    }
+
+    /// What the camera keeps in the centre of the view
+    pub fn follow(&self) -> Follow {
+        self.follow
+    }
+
+    /// The second run of the scenario, when comparing settings
+    pub fn comparison(&self) -> Option<&Run> {
+        self.comparison.as_ref()
+    }
+
+    /// How the comparison is drawn
+    pub fn compare_view(&self) -> CompareView {
+        self.compare_view
+    }
+
+    /// How far the comparison has drifted from the simulation, when comparing settings
+    pub fn divergence(&self) -> Option<Divergence> {
+        let comparison = self.comparison.as_ref()?;
+        Divergence::measure(self.simulation.as_ref(), comparison.simulation())
+    }
+
+    /// Apply a message to the state
     pub fn msg(&mut self, msg: &Msg) {
         match msg {
             Msg::AdvanceClock(dt) => {
                 self.clock += dt;
             }
             Msg::MouseDown(x, y) => {
-                self.mouse.set_pressed(true);
-                self.mouse.set_pos(*x, *y);
+                self.mouse.press(*x, *y);
+                // The physics holds still while an object is dragged
+                self.drag = self.grab(*x, *y);
+                if let Some(drag) = self.drag {
+                    self.paused = true;
+                    self.selected = Some(drag.index);
+                }
+            }
+            Msg::PanStart(x, y) => {
+                self.cancel_press();
+                self.mouse.press_to_pan(*x, *y);
+            }
+            Msg::TouchStart(touches) => match (self.gesture, Pinch::of(touches)) {
+                (None, None) => {
+                    if let Some((x, y)) = touches.first() {
+                        self.gesture = Some(Gesture::Press);
+                        self.msg(&Msg::MouseDown(*x, *y));
+                    }
+                }
+                (_, Some(pinch)) => {
+                    // A press already under way by the first finger gives way to the pinch
+                    self.cancel_press();
+                    self.gesture = Some(Gesture::Pinch(pinch));
+                }
+                (Some(_), None) => {}
+            },
+            Msg::TouchMove(touches) => match (self.gesture, Pinch::of(touches)) {
+                (Some(Gesture::Press), _) => {
+                    if let Some((x, y)) = touches.first() {
+                        self.msg(&Msg::MouseMove(*x, *y));
+                    }
+                }
+                (Some(Gesture::Pinch(from)), Some(to)) => {
+                    self.pinch(from, to);
+                    self.gesture = Some(Gesture::Pinch(to));
+                }
+                _ => {}
+            },
+            Msg::TouchEnd(touches) => {
+                if touches.is_empty() {
+                    if self.gesture == Some(Gesture::Press) {
+                        self.msg(&Msg::MouseUp);
+                    }
+                    self.gesture = None;
+                    return;
+                }
+                // Lifting one of three fingers carries on pinching with the other two
+                self.gesture = match Pinch::of(touches) {
+                    Some(pinch) => Some(Gesture::Pinch(pinch)),
+                    None => Some(Gesture::Finished),
+                };
             }
             Msg::MouseUp => {
+                if self.mouse.panning() {
+                    self.mouse.set_pressed(false);
+                    return;
+                }
+                if let Some(drag) = self.drag.take() {
+                    self.paused = drag.was_paused;
+                    self.mouse.set_pressed(false);
+                    return;
+                }
+                // A click without dragging uses the tool on what is under the mouse
+                if self.mouse.get_pressed() && !self.mouse.dragged() {
+                    let (x, y) = self.mouse.get_pos();
+                    let (x, y) = (x as i32, y as i32);
+                    match self.tool {
+                        Tool::Select | Tool::Slingshot(_) => self.selected = self.pick(x, y),
+                        Tool::Add(kind) => {
+                            if let Some(position) = self.place(x, y) {
+                                self.tool = Tool::Select;
+                                self.add_object(&ObjectSpec::new(kind, position.into()));
+                            }
+                        }
+                    }
+                }
                 self.mouse.set_pressed(false);
             }
+            Msg::Select(selected) => {
+                self.selected = selected.filter(|index| *index < self.object_count());
+            }
+            Msg::CanvasSize(width, height) => {
+                self.canvas_size = (*width, *height);
+                // A split view divides the canvas into halves of the same shape as the whole
+                if *width > 0 && *height > 0 {
+                    self.camera.set_aspect(*width as f32 / *height as f32);
+                }
+            }
             Msg::MouseMove(x, y) => {
                 if !self.mouse.get_pressed() {
                     return;
                 }
 
+                if let Some(drag) = self.drag {
+                    self.mouse.set_pos(*x, *y);
+                    let edit = self.view_ray(*x, *y).and_then(|(_, origin, direction)| {
+                        drag.follow(origin, direction, self.facing())
+                    });
+                    if let Some(edit) = edit {
+                        self.msg(&Msg::EditObject(drag.index, edit));
+                        // An arrow sets how the object starts, as well as how it moves now
+                        if let Handle::Arrow(_) = drag.handle {
+                            self.msg(&Msg::EditInitial(drag.index, edit));
+                        }
+                    }
+                    return;
+                }
+
                 let (old_x, old_y) = self.mouse.get_pos();
 
+                if self.mouse.panning() {
+                    // Moving the view by hand stops it following anything
+                    self.follow = Follow::Free;
+                    let (dx, dy) = (x - old_x as i32, y - old_y as i32);
+                    let (width, height) = self.view_size();
+                    self.camera.pan(dx as f32, dy as f32, width, height);
+                    self.mouse.set_pos(*x, *y);
+                    return;
+                }
+
                 let x_delta = old_x as i32 - x;
                 let y_delta = y - old_y as i32;
 
@@ -102,18 +524,637 @@ impl State {
                 self.time_scale = *time_scale;
             }
             Msg::UpdateSimulation(dt) => {
-                self.simulation.update(*dt as f64 * self.time_scale as f64);
+                if !self.paused {
+                    let steps = self.stepper.advance(*dt as f64 * self.time_scale as f64);
+                    for _ in 0..steps {
+                        self.step();
+                    }
+                }
+                // Objects can be moved by hand while paused, so follow them either way
+                self.camera.animate(*dt);
+                self.fit_scene();
+                self.follow_target();
+            }
+            Msg::Step => {
+                self.step();
+                // Show exactly where the step ended, rather than part way along it
+                self.stepper.reset();
+                self.capture_poses();
+                self.fit_scene();
+                self.follow_target();
+            }
+            Msg::CameraTarget(target) => {
+                self.follow = Follow::Free;
+                self.camera.set_target(Point3::from(target.cast()));
+            }
+            Msg::Projection(mode) => {
+                self.camera.set_projection_mode(*mode);
+            }
+            Msg::LookFrom(preset) => {
+                self.camera.look_from(*preset);
+            }
+            Msg::FrameAll => {
+                if let Some(bounds) = self.scene_bounds() {
+                    self.frame(bounds);
+                }
+            }
+            Msg::FrameSelection => {
+                let objects = self.simulation.get_objects();
+                let selected = self.selected.and_then(|index| objects.get(index));
+                if let Some(bounds) = selected.and_then(|object| Bounds::of(&[*object])) {
+                    self.frame(bounds);
+                }
+            }
+            Msg::Follow(follow) => {
+                self.follow = *follow;
+                self.follow_target();
+            }
+            Msg::PhysicsDt(dt) => {
+                self.stepper.set_dt(*dt);
             }
             Msg::Offset(offset) => {
                 self.simulation.set_offset(*offset as f64);
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.set_offset(*offset as f64);
+                }
+                // Changing the offset changes the energy of every dipole, so drift is measured
+                // from here on
+                self.diagnostics.reset(self.simulation.as_ref());
+            }
+            Msg::EditObject(index, edit) => {
+                if let Err(err) = self.simulation.edit_object(*index, *edit) {
+                    logging::warn(&format!("Could not edit object {}: {}", index, err));
+                    return;
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    // The comparison shares the scenario, so whatever suits one suits the other
+                    let _ = comparison.edit_object(*index, *edit);
+                }
+                // Draw the object where it was put rather than sliding it there
+                self.capture_poses();
+                self.diagnostics.reset(self.simulation.as_ref());
+            }
+            Msg::EditInitial(index, edit) => {
+                if let Err(err) = self.simulation.edit_initial(*index, *edit) {
+                    logging::warn(&format!("Could not edit object {}: {}", index, err));
+                    return;
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    let _ = comparison.edit_initial(*index, *edit);
+                }
+            }
+            Msg::Tool(tool) => {
+                if let Tool::Add(kind) = tool {
+                    if let Err(err) = self.can_add(*kind) {
+                        self.add_error = Some(err);
+                        return;
+                    }
+                }
+                self.tool = *tool;
+            }
+            Msg::AddObject(object) => {
+                self.add_object(object);
+            }
+            Msg::DuplicateObject(index) => {
+                let mut objects = Scenario::capture(self.simulation.as_ref(), "").objects;
+                if *index >= objects.len() {
+                    return;
+                }
+                let mut object = objects.swap_remove(*index);
+                object.position[0] += DUPLICATE_SPACING;
+                self.add_object(&object);
+            }
+            Msg::DeleteObject(index) => {
+                if let Err(err) = self.simulation.remove_object(*index) {
+                    logging::warn(&format!("Could not delete object {}: {}", index, err));
+                    return;
+                }
+                if let Some(comparison) = &mut self.comparison {
+                    let _ = comparison.remove_object(*index);
+                }
+                self.selected = match self.selected {
+                    Some(selected) if selected == *index => None,
+                    Some(selected) if selected > *index => Some(selected - 1),
+                    selected => selected,
+                };
+                self.objects_changed();
+            }
+            Msg::Paused(paused) => {
+                self.paused = *paused;
+            }
+            Msg::Integrator(integrator) => {
+                self.integrator = *integrator;
+                self.simulation.set_integrator(*integrator);
+            }
+            Msg::ExternalField(field) => {
+                self.simulation.set_external_field(*field);
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.set_external_field(*field);
+                }
+                // The field changes the potential energy, so drift is measured from here on
+                self.diagnostics.reset(self.simulation.as_ref());
+            }
+            Msg::Compare(Some(settings)) => {
+                self.simulation.reset();
+                let scenario = Scenario::capture(self.simulation.as_ref(), "");
+                self.compare(&scenario, *settings);
+                self.restart();
+            }
+            Msg::Compare(None) => {
+                self.comparison = None;
+            }
+            Msg::CompareView(view) => {
+                self.compare_view = *view;
+            }
+            Msg::DriftThreshold(threshold) => {
+                self.diagnostics.set_threshold(*threshold);
+            }
+            Msg::CloseApproachDistance(distance) => {
+                self.close_approach_distance = *distance;
+                self.approaching = false;
             }
             Msg::ResetSimulation => {
                 self.simulation.reset();
+                if let Some(comparison) = &mut self.comparison {
+                    comparison.reset();
+                }
+                self.restart();
+            }
+            Msg::LoadScenario(scenario) => match scenario.build() {
+                Ok(mut simulation) => {
+                    simulation.set_integrator(self.integrator);
+                    self.simulation = simulation;
+                    self.live = None;
+                    if let Some(comparison) = &self.comparison {
+                        let settings = *comparison.settings();
+                        self.compare(scenario, settings);
+                    }
+                    self.restart();
+                }
+                Err(err) => {
+                    logging::warn(&format!("Could not load scenario: {}", err));
+                }
+            },
+            Msg::RecordTrajectory(record) => {
+                self.record_trajectory = *record;
+            }
+            Msg::ExportStride(stride) => {
+                self.trajectory.set_stride(*stride as usize);
+            }
+            Msg::ChartSeries(series, shown) => {
+                self.chart_series.retain(|s| s != series);
+                if *shown {
+                    self.chart_series.push(*series);
+                }
+            }
+            Msg::ChartWindow(window) => {
+                self.history.set_window(*window as f64);
+            }
+            Msg::LoadTrajectory(bytes) => {
+                let playback =
+                    TrajectoryReader::open(Cursor::new(bytes.clone())).and_then(Playback::new);
+                match playback {
+                    Ok(playback) => {
+                        let simulation =
+                            std::mem::replace(&mut self.simulation, Box::new(playback));
+                        // A recording can't be run again with other settings
+                        let comparison = self.comparison.take();
+                        if self.live.is_none() {
+                            self.live = Some((simulation, comparison));
+                        }
+                        self.restart();
+                    }
+                    Err(err) => {
+                        logging::warn(&format!("Could not load trajectory: {}", err));
+                    }
+                }
+            }
+            Msg::LeavePlayback => {
+                if let Some((simulation, comparison)) = self.live.take() {
+                    self.simulation = simulation;
+                    self.simulation.set_integrator(self.integrator);
+                    self.comparison = comparison;
+                    self.msg(&Msg::ResetSimulation);
+                }
+            }
+        }
+    }
+
+    /// Advance the simulation, and the comparison alongside it, by one physics step
+    fn step(&mut self) {
+        let dt = self.stepper.dt();
+        self.capture_poses();
+        self.simulation.update(dt);
+        if let Some(comparison) = &mut self.comparison {
+            comparison.update(dt);
+        }
+
+        self.sim_time += dt;
+        let exceeded = self.diagnostics.exceeded();
+        self.diagnostics
+            .update(self.simulation.as_ref(), self.sim_time);
+        self.history
+            .record(self.sim_time, *self.diagnostics.current());
+        if self.record_trajectory {
+            self.trajectory
+                .record(self.simulation.as_ref(), self.sim_time);
+        }
+
+        self.events.push(SimulationEvent::Step {
+            time: self.sim_time,
+            observables: *self.diagnostics.current(),
+        });
+        self.check_close_approach();
+        if !exceeded && self.diagnostics.exceeded() {
+            self.events.push(SimulationEvent::DriftExceeded {
+                drift: *self.diagnostics.latest(),
+            });
+        }
+    }
+
+    fn object_count(&self) -> usize {
+        self.simulation.get_objects().len()
+    }
+
+    /// The object drawn at canvas coordinates `x`, `y`, measured in pixels from the top left
+    fn pick(&self, x: i32, y: i32) -> Option<usize> {
+        let (in_comparison, origin, direction) = self.view_ray(x, y)?;
+        let simulation = match (&self.comparison, in_comparison) {
+            (Some(comparison), true) => comparison.simulation(),
+            _ => self.simulation.as_ref(),
+        };
+        picking::pick(simulation, origin, direction)
+    }
+
+    /// Where a click at canvas coordinates `x`, `y` meets the plane through the centre of the
+    /// view that faces the camera
+    fn place(&self, x: i32, y: i32) -> Option<Vector3<f64>> {
+        let (_, origin, direction) = self.view_ray(x, y)?;
+        let target = self.camera.target().coords.cast();
+        gizmo::ray_plane(origin, direction, target, self.facing())
+    }
+
+    /// Let go of whatever the mouse or a finger was pressing, without it counting as a click
+    fn cancel_press(&mut self) {
+        if let Some(drag) = self.drag.take() {
+            self.paused = drag.was_paused;
+        }
+        self.mouse.set_pressed(false);
+    }
+
+    /// Zoom, pan and twist the view as two fingers move from `from` to `to`
+    fn pinch(&mut self, from: Pinch, to: Pinch) {
+        let (before, after) = (from.midpoint(), to.midpoint());
+        let (dx, dy) = (after.0 - before.0, after.1 - before.1);
+        if dx != 0. || dy != 0. {
+            // Moving the view by hand stops it following anything
+            self.follow = Follow::Free;
+            let (width, height) = self.view_size();
+            self.camera.pan(dx, dy, width, height);
+        }
+
+        // Spreading the fingers brings the scene closer in proportion
+        if to.spread() > 0. {
+            let radius = self.camera.orbit_radius();
+            self.camera
+                .zoom(radius * (from.spread() / to.spread() - 1.));
+        }
+
+        self.camera.orbit_left_right(from.angle() - to.angle());
+    }
+
+    /// Width and height in pixels of the view of the simulation
+    fn view_size(&self) -> (f32, f32) {
+        let (width, height) = (self.canvas_size.0 as f32, self.canvas_size.1 as f32);
+        match (&self.comparison, self.compare_view) {
+            (Some(_), CompareView::Split) => (width / 2., height / 2.),
+            _ => (width, height),
+        }
+    }
+
+    /// Keep the zoom limits and clip planes of the camera fitted to the scene
+    fn fit_scene(&mut self) {
+        if let Some(bounds) = self.scene_bounds() {
+            let center = Point3::from(bounds.center.cast());
+            self.camera.set_scene(center, bounds.radius as f32);
+        }
+    }
+
+    /// Look at all of what is in `bounds`, and stop following anything
+    fn frame(&mut self, bounds: Bounds) {
+        self.follow = Follow::Free;
+        let center = Point3::from(bounds.center.cast());
+        self.camera.frame(center, bounds.radius as f32);
+    }
+
+    /// Point the camera at what it follows, where that is drawn this frame
+    fn follow_target(&mut self) {
+        // Following an object while it is dragged would pull it away from the mouse
+        if self.drag.is_some() {
+            return;
+        }
+        let objects = self.simulation.get_objects();
+        let alpha = self.stepper.alpha();
+        let drawn = |index: usize| {
+            let object = objects[index];
+            let pose = (object.get_pos(), object.get_orientation());
+            self.previous.interpolate(index, pose.0, pose.1, alpha).0
+        };
+
+        let target = match self.follow {
+            Follow::Free => return,
+            Follow::Selected => match self.selected.filter(|index| *index < objects.len()) {
+                Some(index) => drawn(index),
+                None => return,
+            },
+            Follow::CenterOfMass => {
+                let mass: f64 = objects.iter().map(|object| object.get_mass()).sum();
+                if mass <= 0. {
+                    return;
+                }
+                let moment: Vector3<f64> = (0..objects.len())
+                    .map(|index| drawn(index) * objects[index].get_mass())
+                    .sum();
+                moment / mass
+            }
+        };
+        self.camera.set_target(Point3::from(target.cast()));
+    }
+
+    /// The direction the camera looks in
+    fn facing(&self) -> Vector3<f64> {
+        self.camera.forward().cast()
+    }
+
+    /// The ray from the eye through canvas coordinates `x`, `y`, and whether that point is in
+    /// the view of the comparison rather than of the simulation. `None` if the point is outside
+    /// every view.
+    fn view_ray(&self, x: i32, y: i32) -> Option<(bool, Point3<f64>, Vector3<f64>)> {
+        let (width, height) = (self.canvas_size.0 as f32, self.canvas_size.1 as f32);
+        let (x, y) = (x as f32, y as f32);
+
+        // Where the point falls in the view it is in, from -1 to 1 and with y up
+        let (in_comparison, ndc) = match (&self.comparison, self.compare_view) {
+            (Some(_), CompareView::Split) => {
+                // Each run has a square half of the canvas, centred vertically
+                let (half, top) = (width / 2., height / 4.);
+                let ndc_y = 1. - 2. * (y - top) / (height / 2.);
+                if x < half {
+                    (false, (2. * x / half - 1., ndc_y))
+                } else {
+                    (true, (2. * (x - half) / half - 1., ndc_y))
+                }
+            }
+            _ => (false, (2. * x / width - 1., 1. - 2. * y / height)),
+        };
+        if ndc.0.abs() > 1. || ndc.1.abs() > 1. {
+            return None;
+        }
+
+        let (origin, direction) = self.camera.ray(ndc.0, ndc.1);
+        Some((in_comparison, origin.cast(), direction.cast()))
+    }
+
+    /// Start dragging a handle of the gizmo of the selected object, or the object itself, if
+    /// either is at canvas coordinates `x`, `y`
+    fn grab(&self, x: i32, y: i32) -> Option<Drag> {
+        let (in_comparison, origin, direction) = self.view_ray(x, y)?;
+        if in_comparison {
+            return None;
+        }
+
+        if let Tool::Slingshot(motion) = self.tool {
+            // Arrows can be pulled out of any object, which selects it
+            let index = picking::pick(self.simulation.as_ref(), origin, direction)?;
+            let object = self.simulation.get_objects()[index];
+            if !motion.applies_to(object.get_type()) {
+                return None;
+            }
+            return Some(Drag {
+                index,
+                handle: Handle::Arrow(motion),
+                grab: object.get_pos(),
+                position: object.get_pos(),
+                orientation: object.get_orientation(),
+                was_paused: self.paused,
+            });
+        }
+
+        let gizmo = self.gizmo()?;
+        let index = self.selected?;
+        let (handle, grab) = match gizmo.hit(origin, direction) {
+            Some(hit) => hit,
+            None if picking::pick(self.simulation.as_ref(), origin, direction) == Some(index) => {
+                let grab = gizmo::ray_plane(origin, direction, gizmo.center, self.facing())?;
+                (Handle::Plane, grab)
+            }
+            None => return None,
+        };
+        let object = self.simulation.get_objects()[index];
+        Some(Drag {
+            index,
+            handle,
+            grab,
+            position: object.get_pos(),
+            orientation: object.get_orientation(),
+            was_paused: self.paused,
+        })
+    }
+
+    /// Add an object to the simulation and the comparison, and select it
+    fn add_object(&mut self, object: &ObjectSpec) {
+        if let Err(err) = self.can_add(object.kind) {
+            logging::warn(&format!("Could not add object: {}", err));
+            self.add_error = Some(err);
+            return;
+        }
+        self.add_error = None;
+        if self.object_count() == 0 {
+            // An empty simulation can take either kind of object, so start one of the right kind
+            let scenario = Scenario {
+                name: String::new(),
+                objects: vec![object.clone()],
+                external_field: self.simulation.get_external_field().into(),
+            };
+            self.msg(&Msg::LoadScenario(scenario));
+            self.selected = Some(0).filter(|index| *index < self.object_count());
+            return;
+        }
+
+        let index = match self.simulation.add_object(object) {
+            Ok(index) => index,
+            Err(err) => {
+                logging::warn(&format!("Could not add object: {}", err));
+                self.add_error = Some(err);
+                return;
+            }
+        };
+        if let Some(comparison) = &mut self.comparison {
+            let _ = comparison.add_object(object);
+        }
+        self.selected = Some(index);
+        self.objects_changed();
+    }
+
+    /// Remember where everything is, to interpolate from during the next step
+    fn capture_poses(&mut self) {
+        self.previous = Poses::capture(self.simulation.as_ref());
+        self.comparison_previous = match &self.comparison {
+            Some(comparison) => Poses::capture(comparison.simulation()),
+            None => Poses::default(),
+        };
+    }
+
+    /// Run `scenario` alongside the simulation with other settings
+    fn compare(&mut self, scenario: &Scenario, settings: Settings) {
+        match Run::new(scenario, settings) {
+            Ok(comparison) => self.comparison = Some(comparison),
+            Err(err) => {
+                self.comparison = None;
+                logging::warn(&format!("Could not start comparison: {}", err));
+            }
+        }
+    }
+
+    /// Start the clock, the recording and the drift measurements over from the current state
+    fn restart(&mut self) {
+        if self
+            .selected
+            .is_some_and(|index| index >= self.object_count())
+        {
+            self.selected = None;
+        }
+        self.sim_time = 0.;
+        self.add_error = None;
+        self.trajectory.clear();
+        self.diagnostics.reset(self.simulation.as_ref());
+        self.history.clear();
+        self.approaching = false;
+        self.stepper.reset();
+        self.capture_poses();
+        self.fit_scene();
+        self.events.push(SimulationEvent::Reset);
+    }
+
+    /// Carry on after objects were added or removed. The clock and the chart history run on, and
+    /// the drift monitor measures from the new set of objects. A trajectory being recorded starts
+    /// again, as every frame of one holds the same objects.
+    fn objects_changed(&mut self) {
+        self.trajectory.clear();
+        self.diagnostics.reset(self.simulation.as_ref());
+        self.approaching = false;
+        self.capture_poses();
+        self.fit_scene();
+    }
+
+    /// Report two objects coming closer than the close approach distance, once per approach
+    fn check_close_approach(&mut self) {
+        match batch::closest_pair(self.simulation.as_ref()) {
+            Some((first, second, distance)) if distance < self.close_approach_distance => {
+                if !self.approaching {
+                    self.events.push(SimulationEvent::CloseApproach {
+                        time: self.sim_time,
+                        first,
+                        second,
+                        distance,
+                    });
+                }
+                self.approaching = true;
             }
+            _ => self.approaching = false,
+        }
+    }
+}
+
+/// What clicking on the canvas does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tool {
+    /// Select the object clicked on
+    Select,
+    /// Put a new object of a kind where the canvas is clicked, then go back to selecting
+    Add(Objects),
+    /// Drag an arrow out of an object to set how it starts moving
+    Slingshot(Motion),
+}
+
+/// What the camera keeps in the centre of the view as the simulation runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Follow {
+    /// Nothing. The camera stays looking where it was put.
+    Free,
+    /// The centre of mass of all the objects
+    CenterOfMass,
+    /// The selected object, if there is one
+    Selected,
+}
+
+/// How the scene is projected onto the canvas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Farther things are drawn smaller
+    Perspective,
+    /// Things are drawn the same size however far away, as in diagrams
+    Orthographic,
+}
+
+/// A standard direction to look at the scene from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    /// Looking down the y axis
+    Top,
+    /// Looking along the z axis, as when starting
+    Front,
+    /// Looking along the x axis
+    Side,
+    /// Looking equally along all three axes
+    Isometric,
+}
+
+impl Preset {
+    /// Every preset, in the order they are offered
+    pub const ALL: [Preset; 4] = [Preset::Top, Preset::Front, Preset::Side, Preset::Isometric];
+
+    /// Short name used by the controls and the web client
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Top => "top",
+            Preset::Front => "front",
+            Preset::Side => "side",
+            Preset::Isometric => "isometric",
         }
     }
+
+    /// Human readable name
+    pub fn label(&self) -> &'static str {
+        match self {
+            Preset::Top => "Top",
+            Preset::Front => "Front",
+            Preset::Side => "Side",
+            Preset::Isometric => "Isometric",
+        }
+    }
+
+    /// Yaw and pitch of the camera about its target, in radians
+    pub fn angles(&self) -> (f32, f32) {
+        match self {
+            Preset::Top => (0., MAX_PITCH),
+            Preset::Front => (0., 0.),
+            Preset::Side => (PI / 2., 0.),
+            Preset::Isometric => (PI / 4., (1. / 2f32.sqrt()).atan()),
+        }
+    }
+}
+
+/// How a comparison between two runs is drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompareView {
+    /// Each run in its own half of the canvas
+    Split,
+    /// The comparison faded over the simulation
+    Ghost,
 }
 
+/// Read only access to the `State`. Changes have to go through `msg`.
 pub struct StateWrapper(State);
 
 impl Deref for StateWrapper {
@@ -125,20 +1166,104 @@ impl Deref for StateWrapper {
 }
 
 impl StateWrapper {
+    /// Apply a message to the state
     pub fn msg(&mut self, msg: &Msg) {
         let _ = &self.0.msg(msg);
     }
 }
 
+/// Everything that can change the `State`
 pub enum Msg {
+    /// Real time has passed, in seconds
     AdvanceClock(f32),
+    /// A mouse button was pressed at canvas coordinates
     MouseDown(i32, i32),
+    /// A press that pans the view started at canvas coordinates, as with the right mouse button
+    PanStart(i32, i32),
+    /// A finger was put on the canvas, leaving these on it, in canvas coordinates
+    TouchStart(Vec<(i32, i32)>),
+    /// Fingers on the canvas moved to these canvas coordinates
+    TouchMove(Vec<(i32, i32)>),
+    /// A finger was lifted from the canvas, leaving these on it. A finger put down and lifted
+    /// again without moving taps, which uses the tool like a click.
+    TouchEnd(Vec<(i32, i32)>),
+    /// The mouse button was released. Without dragging in between, this selects the object
+    /// under the mouse.
     MouseUp,
+    /// Select the object with this index, or nothing
+    Select(Option<usize>),
+    /// The canvas was resized to this width and height in pixels
+    CanvasSize(u32, u32),
+    /// This many seconds of real time have passed. Scaled by the time scale, they advance the
+    /// simulation by whole physics steps, with the remainder carried over.
     UpdateSimulation(f32),
+    /// The mouse moved to canvas coordinates
     MouseMove(i32, i32),
+    /// Move the camera towards or away from the scene
     Zoom(f32),
+    /// Look at and orbit about this point, and stop following anything
+    CameraTarget(Vector3<f64>),
+    /// What the camera keeps in the centre of the view
+    Follow(Follow),
+    /// Draw the view in perspective or flat
+    Projection(Projection),
+    /// Swing the camera round to look from a preset direction
+    LookFrom(Preset),
+    /// Fit everything in the view
+    FrameAll,
+    /// Fit the selected object in the view
+    FrameSelection,
+    /// Whether the scene is drawn at all
     ShowScenery(bool),
+    /// Seconds of simulated time per second of real time
     TimeScale(f32),
+    /// Distance from the centre to each charge of every dipole
     Offset(f32),
+    /// Change one value of an object, in the simulation and the comparison
+    EditObject(usize, Edit),
+    /// Change one value of the state an object resets to, in the simulation and the comparison
+    EditInitial(usize, Edit),
+    /// What clicking on the canvas does
+    Tool(Tool),
+    /// Add an object, in the simulation and the comparison, and select it
+    AddObject(ObjectSpec),
+    /// Add a copy of the current state of an object beside it, and select the copy
+    DuplicateObject(usize),
+    /// Remove an object from the simulation and the comparison
+    DeleteObject(usize),
+    /// Hold the simulation still, or let it run again
+    Paused(bool),
+    /// Advance by a single physics step, whether paused or not
+    Step,
+    /// Seconds of simulated time per physics step
+    PhysicsDt(f64),
+    /// The integrator the simulation is stepped with
+    Integrator(Integrator),
+    /// Uniform field applied on top of the field of the objects
+    ExternalField(Vector3<f64>),
+    /// Run the scenario a second time from the start, in lock-step with other settings, or stop
+    /// comparing
+    Compare(Option<Settings>),
+    /// How the comparison is drawn
+    CompareView(CompareView),
+    /// Relative drift of a conserved quantity above which the simulation is flagged
+    DriftThreshold(f64),
+    /// Distance between two objects below which a close approach is reported
+    CloseApproachDistance(f64),
+    /// Return the simulation to its initial state
     ResetSimulation,
+    /// Replace the simulation with one starting from a scenario
+    LoadScenario(Scenario),
+    /// Whether frames are recorded to the trajectory
+    RecordTrajectory(bool),
+    /// Steps between recorded trajectory frames
+    ExportStride(u32),
+    /// Replace the simulation with playback of a binary trajectory file
+    LoadTrajectory(Vec<u8>),
+    /// Go back from playing a recording to the simulation it replaced, from its start
+    LeavePlayback,
+    /// Show or hide a series on the chart
+    ChartSeries(Series, bool),
+    /// Seconds of simulated time shown on the chart
+    ChartWindow(f32),
 }
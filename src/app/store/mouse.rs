@@ -1,8 +1,14 @@
+/// Movement in pixels after which a press counts as a drag rather than a click
+const DRAG_THRESHOLD: i32 = 4;
+
 #[derive(Default)]
 pub struct Mouse {
     pressed: bool,
     x: u16,
     y: u16,
+    down: (i32, i32),
+    dragged: bool,
+    panning: bool,
 }
 
 impl Mouse {
@@ -17,9 +23,37 @@ impl Mouse {
     pub fn set_pos(&mut self, x: i32, y: i32) {
         self.x = x as u16;
         self.y = y as u16;
+        if (x - self.down.0).abs().max((y - self.down.1).abs()) > DRAG_THRESHOLD {
+            self.dragged = true;
+        }
     }
 
     pub fn get_pos(&self) -> (u16, u16) {
         (self.x, self.y)
     }
+
+    /// Start a press at `x`, `y`
+    pub fn press(&mut self, x: i32, y: i32) {
+        self.pressed = true;
+        self.down = (x, y);
+        self.dragged = false;
+        self.panning = false;
+        self.set_pos(x, y);
+    }
+
+    /// Start a press at `x`, `y` that pans the view rather than orbiting it
+    pub fn press_to_pan(&mut self, x: i32, y: i32) {
+        self.press(x, y);
+        self.panning = true;
+    }
+
+    /// Whether the press pans the view
+    pub fn panning(&self) -> bool {
+        self.panning
+    }
+
+    /// Whether the mouse has moved far enough since it was pressed to be dragging
+    pub fn dragged(&self) -> bool {
+        self.dragged
+    }
 }
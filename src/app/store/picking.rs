@@ -0,0 +1,63 @@
+//! Finding the object under the mouse
+
+use crate::simulation::dipole::Object;
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use nalgebra::{Point3, Vector3};
+
+/// Radius of the spheres objects are drawn with
+pub const SPHERE_RADIUS: f64 = 0.5;
+
+/// Distance along a ray with unit `direction` to where it first enters a sphere, if it does
+pub fn ray_sphere(
+    origin: Point3<f64>,
+    direction: Vector3<f64>,
+    center: Vector3<f64>,
+    radius: f64,
+) -> Option<f64> {
+    let to_center = center - origin.coords;
+    let along = to_center.dot(&direction);
+    let miss_squared = to_center.norm_squared() - along * along;
+    if miss_squared > radius * radius {
+        return None;
+    }
+
+    let half_chord = (radius * radius - miss_squared).sqrt();
+    let (entry, exit) = (along - half_chord, along + half_chord);
+    if exit < 0. {
+        None
+    } else {
+        Some(entry.max(0.))
+    }
+}
+
+/// Centres of the spheres `object` is drawn with: one for each charge of a dipole, or the charge
+/// itself
+pub fn sphere_centers(object: &dyn Object) -> Vec<Vector3<f64>> {
+    match object.get_type() {
+        Objects::Dipole => {
+            let arm = object.get_orientation() * object.get_offset();
+            vec![object.get_pos() - arm, object.get_pos() + arm]
+        }
+        Objects::Charge => vec![object.get_pos()],
+    }
+}
+
+/// The index of the nearest object of `simulation` whose spheres the ray passes through
+pub fn pick(
+    simulation: &dyn Simulatable,
+    origin: Point3<f64>,
+    direction: Vector3<f64>,
+) -> Option<usize> {
+    let mut nearest: Option<(usize, f64)> = None;
+    for (index, object) in simulation.get_objects().iter().enumerate() {
+        for center in sphere_centers(*object) {
+            if let Some(distance) = ray_sphere(origin, direction, center, SPHERE_RADIUS) {
+                if nearest.is_none_or(|(_, nearest)| distance < nearest) {
+                    nearest = Some((index, distance));
+                }
+            }
+        }
+    }
+    nearest.map(|(index, _)| index)
+}
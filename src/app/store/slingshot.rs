@@ -0,0 +1,59 @@
+//! Setting how objects start moving by dragging arrows out of them
+
+use crate::simulation::dipole::{Edit, Object, Objects};
+use nalgebra::Vector3;
+
+/// Length of an arrow per unit of speed or angular speed
+pub const ARROW_SCALE: f64 = 4.;
+
+/// Which motion of an object an arrow shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+    /// Velocity of the centre of mass
+    Linear,
+    /// Angular velocity of a dipole, pointing along the axis it turns about
+    Angular,
+}
+
+impl Motion {
+    /// Whether objects of a kind can have this motion
+    pub fn applies_to(self, kind: Objects) -> bool {
+        self == Motion::Linear || kind == Objects::Dipole
+    }
+
+    /// The edit setting this motion to `value`
+    pub fn edit(self, value: Vector3<f64>) -> Edit {
+        match self {
+            Motion::Linear => Edit::Velocity(value),
+            Motion::Angular => Edit::AngularVelocity(value),
+        }
+    }
+
+    /// The value of this motion of `object`
+    pub fn of(self, object: &dyn Object) -> Vector3<f64> {
+        match self {
+            Motion::Linear => object.get_velocity(),
+            Motion::Angular => object.get_angular_velocity(),
+        }
+    }
+}
+
+/// An arrow drawn out of an object showing one of its motions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arrow {
+    /// Index of the object
+    pub index: usize,
+    /// Which motion is shown
+    pub motion: Motion,
+    /// Where the arrow starts, at the centre of the object
+    pub start: Vector3<f64>,
+    /// The velocity or angular velocity. The arrow is `ARROW_SCALE` times as long.
+    pub value: Vector3<f64>,
+}
+
+impl Arrow {
+    /// Where the arrow points to
+    pub fn end(&self) -> Vector3<f64> {
+        self.start + self.value * ARROW_SCALE
+    }
+}
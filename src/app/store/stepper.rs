@@ -0,0 +1,100 @@
+//! Advancing the physics in fixed steps however long each frame takes
+
+use crate::simulation::dipole::Simulatable;
+use nalgebra::Vector3;
+
+/// Turns the time that passes between frames into a whole number of equal physics steps, so
+/// that the simulation doesn't depend on the frame rate.
+///
+/// Time left over is carried into the next frame. Time beyond `max_steps` steps is dropped, so
+/// that a long pause between frames, like a backgrounded tab, doesn't stall the page catching up.
+pub struct FixedStep {
+    dt: f64,
+    max_steps: u32,
+    accumulator: f64,
+}
+
+impl FixedStep {
+    /// Steps of `dt` seconds, at most `max_steps` per frame
+    pub fn new(dt: f64, max_steps: u32) -> FixedStep {
+        FixedStep {
+            dt,
+            max_steps,
+            accumulator: 0.,
+        }
+    }
+
+    /// Seconds of simulated time per step
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    /// Change the length of a step
+    pub fn set_dt(&mut self, dt: f64) {
+        if dt > 0. {
+            self.dt = dt;
+            self.accumulator = self.accumulator.min(dt);
+        }
+    }
+
+    /// Add `elapsed` seconds of simulated time and return how many steps to take
+    pub fn advance(&mut self, elapsed: f64) -> u32 {
+        self.accumulator += elapsed.max(0.);
+        let steps = (self.accumulator / self.dt).floor();
+        if steps > self.max_steps as f64 {
+            self.accumulator = 0.;
+            return self.max_steps;
+        }
+        self.accumulator -= steps * self.dt;
+        steps as u32
+    }
+
+    /// How far the time left over is into the next step, from 0 to 1
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.dt).clamp(0., 1.)
+    }
+
+    /// Forget the time left over
+    pub fn reset(&mut self) {
+        self.accumulator = 0.;
+    }
+}
+
+/// The position and orientation of every object at the start of the latest step, so that
+/// frames falling between steps can be drawn part way along it
+#[derive(Default)]
+pub struct Poses(Vec<(Vector3<f64>, Vector3<f64>)>);
+
+impl Poses {
+    /// Record where the objects of `simulation` are now
+    pub fn capture(simulation: &dyn Simulatable) -> Poses {
+        Poses(
+            simulation
+                .get_objects()
+                .iter()
+                .map(|object| (object.get_pos(), object.get_orientation()))
+                .collect(),
+        )
+    }
+
+    /// The position and orientation of the `index`th object `alpha` of the way from its
+    /// recorded pose to the given one. Objects that weren't recorded are shown as they are.
+    pub fn interpolate(
+        &self,
+        index: usize,
+        position: Vector3<f64>,
+        orientation: Vector3<f64>,
+        alpha: f64,
+    ) -> (Vector3<f64>, Vector3<f64>) {
+        let (previous_position, previous_orientation) = match self.0.get(index) {
+            Some(pose) => pose,
+            None => return (position, orientation),
+        };
+
+        let position = previous_position.lerp(&position, alpha);
+        let blended = previous_orientation.lerp(&orientation, alpha);
+        // Orientations are unit vectors, or zero for point charges
+        let orientation = blended.try_normalize(f64::EPSILON).unwrap_or(orientation);
+        (position, orientation)
+    }
+}
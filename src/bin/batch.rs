@@ -0,0 +1,247 @@
+//! Runs a scenario unattended and writes its trajectory, observables and a summary.
+//!
+//! ```text
+//! dipole-batch [scenario.json] [--steps N | --time T] [--dt DT] [--integrator NAME]
+//!              [--stride N] [--collapse-distance D] [--format xyz|csv|binary] [--out DIR]
+//! ```
+//!
+//! Without a scenario file the two dipoles the web client starts with are simulated.
+
+mod common;
+
+use dipole_simulation::simulation::batch::{self, RunConfig, RunSummary};
+use dipole_simulation::simulation::binary_trajectory::{Header, TrajectoryWriter};
+use dipole_simulation::simulation::scenario::Scenario;
+use dipole_simulation::simulation::trajectory::{self, Frame};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: dipole-batch [scenario.json] [options]
+
+options:
+  --stride N         steps between written samples (default 1)
+  --format FORMAT    trajectory format: xyz, csv or binary (default xyz)
+  --out DIR          directory the output files are written to (default .)";
+
+#[derive(Clone, Copy)]
+enum Format {
+    ExtendedXyz,
+    Csv,
+    Binary,
+}
+
+struct Options {
+    scenario: Option<PathBuf>,
+    config: RunConfig,
+    format: Format,
+    out: PathBuf,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        scenario: None,
+        config: RunConfig::default(),
+        format: Format::ExtendedXyz,
+        out: PathBuf::from("."),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        if common::run_option(&mut options.config, arg, &mut value)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--stride" => options.config.stride = common::parse(arg, &value()?)?,
+            "--format" => {
+                options.format = match value()?.as_str() {
+                    "xyz" => Format::ExtendedXyz,
+                    "csv" => Format::Csv,
+                    "binary" => Format::Binary,
+                    other => return Err(format!("unknown format '{}'", other)),
+                }
+            }
+            "--out" => options.out = PathBuf::from(value()?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path => {
+                if options.scenario.is_some() {
+                    return Err("only one scenario can be run at a time".to_string());
+                }
+                options.scenario = Some(PathBuf::from(path));
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+/// Where sampled frames go
+enum TrajectoryOutput {
+    Text(Format, BufWriter<File>),
+    Binary(
+        Option<TrajectoryWriter<BufWriter<File>>>,
+        Option<BufWriter<File>>,
+    ),
+}
+
+impl TrajectoryOutput {
+    fn create(format: Format, out: &Path) -> io::Result<TrajectoryOutput> {
+        Ok(match format {
+            Format::ExtendedXyz => TrajectoryOutput::Text(
+                format,
+                BufWriter::new(File::create(out.join("trajectory.xyz"))?),
+            ),
+            Format::Csv => {
+                let mut file = BufWriter::new(File::create(out.join("trajectory.csv"))?);
+                trajectory::write_csv_header(&mut file)?;
+                TrajectoryOutput::Text(format, file)
+            }
+            Format::Binary => TrajectoryOutput::Binary(
+                None,
+                Some(BufWriter::new(File::create(out.join("trajectory.dptraj"))?)),
+            ),
+        })
+    }
+
+    fn write(&mut self, frame: &Frame, integrator: &str) -> io::Result<()> {
+        match self {
+            TrajectoryOutput::Text(Format::Csv, file) => frame.write_csv(file),
+            TrajectoryOutput::Text(_, file) => frame.write_extended_xyz(file),
+            TrajectoryOutput::Binary(writer, file) => {
+                // The header describes the objects, so it is written with the first frame
+                if let Some(file) = file.take() {
+                    let header = Header::from_frame(frame, &trajectory::units(), integrator);
+                    *writer = Some(TrajectoryWriter::new(file, header)?);
+                }
+                writer.as_mut().unwrap().write_frame(frame)
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            TrajectoryOutput::Text(_, mut file) => file.flush(),
+            TrajectoryOutput::Binary(writer, _) => match writer {
+                Some(writer) => writer.finish().map(|_| ()),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+fn summarize(scenario: &Scenario, config: &RunConfig, summary: &RunSummary, wall: f64) -> String {
+    let mut text = String::new();
+    text += &format!("scenario: {}\n", scenario.name);
+    text += &format!("integrator: {}, dt: {}\n", config.integrator, config.dt);
+    text += &format!(
+        "steps: {}, simulated time: {}\n",
+        summary.steps, summary.time
+    );
+    text += &format!("wall time: {:.3} s\n", wall);
+    let drifts = [
+        (
+            "energy",
+            summary.final_drift.energy,
+            summary.max_drift.energy,
+        ),
+        (
+            "momentum",
+            summary.final_drift.momentum,
+            summary.max_drift.momentum,
+        ),
+        (
+            "angular momentum",
+            summary.final_drift.angular_momentum,
+            summary.max_drift.angular_momentum,
+        ),
+    ];
+    for (name, last, max) in drifts.iter() {
+        text += &format!(
+            "{} drift: final {:.3e} (relative {:.3e}), max {:.3e} (relative {:.3e})\n",
+            name, last.absolute, last.relative, max.absolute, max.relative
+        );
+    }
+    text += &format!("final separation: {}\n", summary.final_separation);
+    if let Some(time) = summary.collapsed_at {
+        text += &format!("collapsed at t = {}\n", time);
+    }
+    match summary.exceeded_at {
+        Some(time) => {
+            text += &format!(
+                "drift exceeded {} at t = {}\n",
+                config.drift_threshold, time
+            )
+        }
+        None => text += &format!("drift stayed below {}\n", config.drift_threshold),
+    }
+    text
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let scenario = common::load_scenario(&options.scenario)?;
+    let mut simulation = scenario.build()?;
+
+    let io_error = |err: io::Error| format!("could not write output: {}", err);
+    fs::create_dir_all(&options.out).map_err(io_error)?;
+    let mut trajectory =
+        TrajectoryOutput::create(options.format, &options.out).map_err(io_error)?;
+    let mut observables =
+        BufWriter::new(File::create(options.out.join("observables.csv")).map_err(io_error)?);
+    batch::write_observables_header(&mut observables).map_err(io_error)?;
+
+    let config = &options.config;
+    let integrator = config.integrator.name();
+    let start = Instant::now();
+    let summary = batch::run(
+        simulation.as_mut(),
+        config,
+        |time, simulation, diagnostics| {
+            trajectory.write(&Frame::capture(simulation, time), integrator)?;
+            batch::write_observables_row(
+                &mut observables,
+                time,
+                diagnostics.current(),
+                diagnostics.latest(),
+            )
+        },
+    )
+    .map_err(io_error)?;
+    let wall = start.elapsed().as_secs_f64();
+
+    trajectory.finish().map_err(io_error)?;
+    observables.flush().map_err(io_error)?;
+
+    let text = summarize(&scenario, config, &summary, wall);
+    fs::write(options.out.join("summary.txt"), &text).map_err(io_error)?;
+    print!("{}", text);
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if common::help_requested(&args) {
+        println!("{}\n{}", USAGE, common::RUN_OPTIONS);
+        return;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(options) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
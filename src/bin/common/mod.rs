@@ -0,0 +1,62 @@
+//! Command line handling shared by the native binaries
+
+use dipole_simulation::simulation::batch::{Length, RunConfig};
+use dipole_simulation::simulation::scenario::Scenario;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Usage text for the options understood by `run_option`
+pub const RUN_OPTIONS: &str = "  --steps N          number of steps to run (default 1000)
+  --time T           simulated time to run for, instead of a number of steps
+  --dt DT            simulated time per step (default 0.001)
+  --integrator NAME  euler, verlet or rk4 (default rk4)
+  --collapse-distance D
+                     stop once two objects are closer than D";
+
+/// Apply one of the options describing how a run is stepped. Returns false if `arg` is not one
+/// of them.
+pub fn run_option(
+    config: &mut RunConfig,
+    arg: &str,
+    value: &mut dyn FnMut() -> Result<String, String>,
+) -> Result<bool, String> {
+    match arg {
+        "--steps" => config.length = Length::Steps(parse(arg, &value()?)?),
+        "--time" => config.length = Length::Time(parse(arg, &value()?)?),
+        "--dt" => {
+            config.dt = parse(arg, &value()?)?;
+            if config.dt <= 0. {
+                return Err("--dt must be positive".to_string());
+            }
+        }
+        "--integrator" => config.integrator = value()?.parse()?,
+        "--collapse-distance" => config.collapse_distance = Some(parse(arg, &value()?)?),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Whether the usage was asked for rather than a run
+pub fn help_requested(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "-h" || arg == "--help")
+}
+
+/// Parse the value given for the option `name`
+pub fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, name))
+}
+
+/// Read the scenario at `path`, or the two dipoles the web client starts with if there is none
+pub fn load_scenario(path: &Option<PathBuf>) -> Result<Scenario, String> {
+    match path {
+        Some(path) => {
+            let json = fs::read_to_string(path)
+                .map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+            Scenario::from_json(&json)
+        }
+        None => Ok(Scenario::two_dipoles()),
+    }
+}
@@ -0,0 +1,150 @@
+//! Runs a scenario over a grid of parameter values and writes a CSV table of the outcomes.
+//!
+//! ```text
+//! dipole-sweep [scenario.json] --param offset=0.05:0.2:4 --param mass[1]=1,2,4
+//!              [--param seed=0:9:10 --jitter J] [--threads N] [--out sweep.csv]
+//! ```
+
+mod common;
+
+use dipole_simulation::simulation::batch::RunConfig;
+use dipole_simulation::simulation::sweep::Sweep;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+const USAGE: &str = "usage: dipole-sweep [scenario.json] --param NAME=VALUES... [options]
+
+parameters are offset, charge, mass (optionally for one object, as in mass[1]) and seed.
+values are a list a,b,c or an evenly spaced range start:end:count.
+cases stop once two objects are closer than 0.2, unless --collapse-distance says otherwise.
+
+options:
+  --param NAME=VALUES
+                     add a dimension to the grid
+  --jitter J         largest random displacement of a position coordinate in seeded cases
+                     (default 0.01)
+  --threads N        number of cases run at once (default: number of cpus)
+  --out FILE         where the table is written (default: stdout)";
+
+struct Options {
+    sweep: Sweep,
+    threads: usize,
+    out: Option<PathBuf>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut scenario = None;
+    let mut axes = vec![];
+    let mut config = RunConfig {
+        collapse_distance: Some(0.2),
+        ..RunConfig::default()
+    };
+    let mut jitter = 0.01;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut out = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        if common::run_option(&mut config, arg, &mut value)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--param" => axes.push(value()?.parse()?),
+            "--jitter" => {
+                jitter = common::parse(arg, &value()?)?;
+                if jitter < 0. {
+                    return Err("--jitter can't be negative".to_string());
+                }
+            }
+            "--threads" => threads = common::parse(arg, &value()?)?,
+            "--out" => out = Some(PathBuf::from(value()?)),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path => {
+                if scenario.is_some() {
+                    return Err("only one scenario can be swept at a time".to_string());
+                }
+                scenario = Some(PathBuf::from(path));
+            }
+        }
+    }
+
+    if axes.is_empty() {
+        return Err("at least one --param is needed".to_string());
+    }
+
+    Ok(Options {
+        sweep: Sweep {
+            scenario: common::load_scenario(&scenario)?,
+            axes,
+            config,
+            jitter,
+        },
+        threads,
+        out,
+    })
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let sweep = &options.sweep;
+    let start = Instant::now();
+    let results = sweep.run(options.threads);
+    let wall = start.elapsed().as_secs_f64();
+
+    let written = match &options.out {
+        Some(path) => File::create(path).map(BufWriter::new).and_then(|mut file| {
+            sweep.write_csv(&results, &mut file)?;
+            file.flush()
+        }),
+        None => sweep.write_csv(&results, &mut io::stdout().lock()),
+    };
+    written.map_err(|err| format!("could not write results: {}", err))?;
+
+    let failed = results
+        .iter()
+        .filter(|result| result.outcome.is_err())
+        .count();
+    let collapsed = results
+        .iter()
+        .filter(|result| matches!(&result.outcome, Ok(summary) if summary.collapsed_at.is_some()))
+        .count();
+    eprintln!(
+        "{} cases ({} collapsed, {} failed) in {:.3} s on {} threads",
+        results.len(),
+        collapsed,
+        failed,
+        wall,
+        options.threads
+    );
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if common::help_requested(&args) {
+        println!("{}\n{}", USAGE, common::RUN_OPTIONS);
+        return;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(options) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
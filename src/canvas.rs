@@ -1,5 +1,6 @@
 use crate::app::App;
 use crate::app::Msg;
+use crate::logging;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -7,14 +8,121 @@ use wasm_bindgen::JsValue;
 use web_sys::WebGlRenderingContext as GL;
 use web_sys::*;
 
+/// Id of the element a client is mounted in when no element is given
 pub static APP_DIV_ID: &str = "dipole-simulation";
 
-pub static CANVAS_WIDTH: i32 = 512;
-pub static CANVAS_HEIGHT: i32 = 512;
+/// Create the element everything a client shows is placed in, inside `mount`. Without a mount
+/// element the one with id `APP_DIV_ID` is used, created at the end of the body if missing.
+///
+/// Nothing inside is looked up by id, so any number of clients can share a page.
+pub fn create_root(mount: Option<HtmlElement>) -> Result<HtmlElement, JsValue> {
+    let window = window().unwrap();
+    let document = window.document().unwrap();
+
+    let mount = match mount {
+        Some(mount) => mount,
+        None => match document.get_element_by_id(APP_DIV_ID) {
+            Some(container) => container.dyn_into()?,
+            None => {
+                let app_div = document.create_element("div")?;
+                app_div.set_id(APP_DIV_ID);
+                document
+                    .body()
+                    .expect("Document body")
+                    .append_child(&app_div)?;
+                app_div.dyn_into()?
+            }
+        },
+    };
+
+    let root: HtmlElement = document.create_element("div")?.dyn_into()?;
+    root.set_class_name("dipole-simulation");
+    root.style().set_property("display", "flex")?;
+    root.style().set_property("height", "100%")?;
+    mount.append_child(&root)?;
+
+    Ok(root)
+}
+
+/// Create the element the canvas is placed in, which anything drawn over the canvas is
+/// positioned against
+pub fn create_stage(root: &HtmlElement) -> Result<HtmlElement, JsValue> {
+    let window = window().unwrap();
+    let document = window.document().unwrap();
+
+    let stage: HtmlElement = document.create_element("div")?.dyn_into()?;
+    let style = stage.style();
+    style.set_property("position", "relative")?;
+    // Take the width left beside the other panels and the height of the row, which is the
+    // container's if it has one. Fullscreen overrides all of this to fill the screen.
+    style.set_property("flex", "1 1 512px")?;
+    style.set_property("min-width", "256px")?;
+    style.set_property("min-height", "256px")?;
+    root.append_child(&stage)?;
+
+    let fullscreen = create_fullscreen_button(&stage)?;
+    stage.append_child(&fullscreen)?;
+
+    Ok(stage)
+}
+
+/// Show `stage` on the whole screen, or go back to the page if something already is
+pub fn toggle_fullscreen(stage: &HtmlElement) -> Result<(), JsValue> {
+    let document = window().unwrap().document().unwrap();
+    if document.fullscreen_element().is_some() {
+        document.exit_fullscreen();
+        Ok(())
+    } else {
+        stage.request_fullscreen()
+    }
+}
+
+/// A button in the corner of the stage that toggles fullscreen
+fn create_fullscreen_button(stage: &HtmlElement) -> Result<HtmlElement, JsValue> {
+    let document = window().unwrap().document().unwrap();
+
+    let button: HtmlElement = document.create_element("button")?.dyn_into()?;
+    button.set_inner_html("Fullscreen");
+    let style = button.style();
+    style.set_property("position", "absolute")?;
+    style.set_property("right", "5px")?;
+    style.set_property("top", "5px")?;
+
+    let target = stage.clone();
+    let handler = move |_event: web_sys::Event| {
+        if let Err(err) = toggle_fullscreen(&target) {
+            logging::warn(&format!("Could not go fullscreen: {:?}", err));
+        }
+    };
+    let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+    button.set_onclick(Some(handler.as_ref().unchecked_ref()));
+    handler.forget();
+
+    Ok(button)
+}
 
-pub fn create_webgl_context(app: Rc<App>) -> Result<WebGlRenderingContext, JsValue> {
-    let canvas = init_canvas(app)?;
+/// Make the drawing buffer of `canvas` as many device pixels as it is shown at, and tell the
+/// app its size in CSS pixels, which is what positions on it are given in. Does nothing while
+/// neither has changed, so it can be called every frame.
+pub fn fit_canvas(canvas: &HtmlCanvasElement, app: &App) {
+    let ratio = window().unwrap().device_pixel_ratio();
+    let width = canvas.client_width().max(1) as u32;
+    let height = canvas.client_height().max(1) as u32;
+    let buffer_width = (width as f64 * ratio).round() as u32;
+    let buffer_height = (height as f64 * ratio).round() as u32;
+
+    let size = app.store.borrow().state.canvas_size();
+    if (canvas.width(), canvas.height()) == (buffer_width, buffer_height) && size == (width, height)
+    {
+        return;
+    }
+    canvas.set_width(buffer_width);
+    canvas.set_height(buffer_height);
+    app.store.borrow_mut().msg(&Msg::CanvasSize(width, height));
+}
 
+/// The WebGL context of `canvas`
+pub fn create_webgl_context(canvas: &HtmlCanvasElement) -> Result<WebGlRenderingContext, JsValue> {
     let gl: WebGlRenderingContext = canvas.get_context("webgl")?.unwrap().dyn_into()?;
 
     gl.clear_color(0.0, 0.0, 0.0, 1.0);
@@ -23,44 +131,46 @@ pub fn create_webgl_context(app: Rc<App>) -> Result<WebGlRenderingContext, JsVal
     Ok(gl)
 }
 
-fn init_canvas(app: Rc<App>) -> Result<HtmlCanvasElement, JsValue> {
+/// Create the canvas the scene is drawn on, filling `stage`, and pass what happens on it to the
+/// app
+pub fn create_canvas(app: Rc<App>, stage: &HtmlElement) -> Result<HtmlCanvasElement, JsValue> {
     let window = window().unwrap();
     let document = window.document().unwrap();
 
     let canvas: HtmlCanvasElement = document.create_element("canvas").unwrap().dyn_into()?;
-
-    canvas.set_width(CANVAS_WIDTH as u32);
-    canvas.set_height(CANVAS_HEIGHT as u32);
+    let style = canvas.style();
+    style.set_property("display", "block")?;
+    style.set_property("width", "100%")?;
+    style.set_property("height", "100%")?;
 
     attach_mouse_down_handler(&canvas, Rc::clone(&app))?;
     attach_mouse_up_handler(&canvas, Rc::clone(&app))?;
     attach_mouse_move_handler(&canvas, Rc::clone(&app))?;
     attach_mouse_wheel_handler(&canvas, Rc::clone(&app))?;
+    attach_context_menu_handler(&canvas)?;
 
     attach_touch_start_handler(&canvas, Rc::clone(&app))?;
     attach_touch_move_handler(&canvas, Rc::clone(&app))?;
     attach_touch_end_handler(&canvas, Rc::clone(&app))?;
 
-    let app_div: HtmlElement = match document.get_element_by_id(APP_DIV_ID) {
-        Some(container) => container.dyn_into()?,
-        None => {
-            let app_div = document.create_element("div")?;
-            app_div.set_id(APP_DIV_ID);
-            app_div.dyn_into()?
-        }
-    };
-
-    app_div.style().set_property("display", "flex")?;
-    app_div.append_child(&canvas)?;
+    // Before the buttons and labels, so they are drawn over it
+    stage.insert_before(&canvas, stage.first_child().as_ref())?;
+    fit_canvas(&canvas, &app);
 
     Ok(canvas)
 }
 
 fn attach_mouse_down_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
     let handler = move |event: web_sys::MouseEvent| {
-        let x = event.client_x();
-        let y = event.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseDown(x, y));
+        let x = event.offset_x();
+        let y = event.offset_y();
+        // The right button pans
+        let msg = if event.button() == 2 {
+            Msg::PanStart(x, y)
+        } else {
+            Msg::MouseDown(x, y)
+        };
+        app.store.borrow_mut().msg(&msg);
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -87,8 +197,8 @@ fn attach_mouse_up_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(
 fn attach_mouse_move_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
     let handler = move |event: web_sys::MouseEvent| {
         event.prevent_default();
-        let x = event.client_x();
-        let y = event.client_y();
+        let x = event.offset_x();
+        let y = event.offset_y();
         app.store.borrow_mut().msg(&Msg::MouseMove(x, y));
     };
 
@@ -115,12 +225,43 @@ fn attach_mouse_wheel_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Resul
     Ok(())
 }
 
+/// Keep the menu from opening on right click, since the right button pans
+fn attach_context_menu_handler(canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+    let handler = move |event: web_sys::MouseEvent| {
+        event.prevent_default();
+    };
+
+    let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("contextmenu", handler.as_ref().unchecked_ref())?;
+    handler.forget();
+
+    Ok(())
+}
+
+/// Where a touch is on the canvas, in pixels from its top left corner
+fn touch_position(canvas: &HtmlCanvasElement, touch: &Touch) -> (i32, i32) {
+    let rect = canvas.get_bounding_client_rect();
+    (
+        touch.client_x() - rect.left() as i32,
+        touch.client_y() - rect.top() as i32,
+    )
+}
+
+/// Where every one of `touches` is on the canvas
+fn touch_positions(canvas: &HtmlCanvasElement, touches: &TouchList) -> Vec<(i32, i32)> {
+    (0..touches.length())
+        .filter_map(|index| touches.item(index))
+        .map(|touch| touch_position(canvas, &touch))
+        .collect()
+}
+
 fn attach_touch_start_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
+    let target = canvas.clone();
     let handler = move |event: web_sys::TouchEvent| {
-        let touch = event.touches().item(0).expect("First Touch");
-        let x = touch.client_x();
-        let y = touch.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseDown(x, y));
+        // Otherwise the browser follows a tap with mouse events, which would click twice
+        event.prevent_default();
+        let touches = touch_positions(&target, &event.touches());
+        app.store.borrow_mut().msg(&Msg::TouchStart(touches));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -131,12 +272,11 @@ fn attach_touch_start_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Resul
 }
 
 fn attach_touch_move_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
+    let target = canvas.clone();
     let handler = move |event: web_sys::TouchEvent| {
         event.prevent_default();
-        let touch = event.touches().item(0).expect("First Touch");
-        let x = touch.client_x();
-        let y = touch.client_y();
-        app.store.borrow_mut().msg(&Msg::MouseMove(x, y));
+        let touches = touch_positions(&target, &event.touches());
+        app.store.borrow_mut().msg(&Msg::TouchMove(touches));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
@@ -147,13 +287,17 @@ fn attach_touch_move_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result
 }
 
 fn attach_touch_end_handler(canvas: &HtmlCanvasElement, app: Rc<App>) -> Result<(), JsValue> {
-    let handler = move |_event: web_sys::TouchEvent| {
-        app.store.borrow_mut().msg(&Msg::MouseUp);
+    let target = canvas.clone();
+    let handler = move |event: web_sys::TouchEvent| {
+        let touches = touch_positions(&target, &event.touches());
+        app.store.borrow_mut().msg(&Msg::TouchEnd(touches));
     };
 
     let handler = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
 
+    // A touch the browser takes over ends just the same
     canvas.add_event_listener_with_callback("touchend", handler.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("touchcancel", handler.as_ref().unchecked_ref())?;
 
     handler.forget();
 
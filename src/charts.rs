@@ -0,0 +1,144 @@
+use crate::app::Series;
+use crate::app::State;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::window;
+use web_sys::CanvasRenderingContext2d;
+use web_sys::HtmlCanvasElement;
+use web_sys::HtmlElement;
+
+pub static CHART_WIDTH: u32 = 400;
+pub static CHART_HEIGHT: u32 = 300;
+
+// Space around the plot area for the axis labels
+static MARGIN_LEFT: f64 = 70.;
+static MARGIN_RIGHT: f64 = 10.;
+static MARGIN_TOP: f64 = 10.;
+static MARGIN_BOTTOM: f64 = 25.;
+
+/// Plots the selected observables against simulated time on a 2D canvas next to the WebGL view
+pub struct Chart {
+    context: CanvasRenderingContext2d,
+}
+
+impl Chart {
+    pub fn new(container: &HtmlElement) -> Result<Chart, JsValue> {
+        let window = window().unwrap();
+        let document = window.document().unwrap();
+
+        let canvas: HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+        canvas.set_width(CHART_WIDTH);
+        canvas.set_height(CHART_HEIGHT);
+        canvas.style().set_property("margin-left", "5px")?;
+        container.append_child(&canvas)?;
+
+        let context: CanvasRenderingContext2d = canvas.get_context("2d")?.unwrap().dyn_into()?;
+
+        Ok(Chart { context })
+    }
+
+    pub fn draw(&self, state: &State) {
+        let ctx = &self.context;
+        let width = CHART_WIDTH as f64;
+        let height = CHART_HEIGHT as f64;
+
+        ctx.set_fill_style(&"black".into());
+        ctx.fill_rect(0., 0., width, height);
+        ctx.set_font("11px sans-serif");
+
+        let plot_width = width - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_height = height - MARGIN_TOP - MARGIN_BOTTOM;
+        ctx.set_stroke_style(&"gray".into());
+        ctx.set_line_width(1.);
+        ctx.stroke_rect(MARGIN_LEFT, MARGIN_TOP, plot_width, plot_height);
+
+        let history = state.history();
+        let series = state.chart_series();
+        let end = match history.samples().last() {
+            Some((time, _)) => *time,
+            None => return,
+        };
+        let start = end - history.window();
+        if series.is_empty() {
+            return;
+        }
+
+        // Autoscale to the visible part of the selected series
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for (_, observables) in history.samples().filter(|(time, _)| *time >= start) {
+            for s in series.iter() {
+                let value = s.value(observables);
+                if value.is_finite() {
+                    min = min.min(value);
+                    max = max.max(value);
+                }
+            }
+        }
+        if !min.is_finite() || !max.is_finite() {
+            return;
+        }
+        let padding = if max - min > f64::EPSILON {
+            (max - min) * 0.05
+        } else {
+            max.abs().max(1.) * 0.1
+        };
+        let (min, max) = (min - padding, max + padding);
+
+        let x = |time: f64| MARGIN_LEFT + (time - start) / (end - start) * plot_width;
+        let y = |value: f64| MARGIN_TOP + (max - value) / (max - min) * plot_height;
+
+        ctx.set_fill_style(&"white".into());
+        for value in [min, (min + max) / 2., max].iter() {
+            let _ = ctx.fill_text(&format!("{:.4e}", value), 2., y(*value) + 4.);
+        }
+        let _ = ctx.fill_text(
+            &format!("t = {:.2}", start.max(0.)),
+            MARGIN_LEFT,
+            height - 8.,
+        );
+        let _ = ctx.fill_text(&format!("t = {:.2}", end), width - 70., height - 8.);
+
+        for (i, s) in series.iter().enumerate() {
+            let color: JsValue = series_color(*s).into();
+
+            ctx.set_stroke_style(&color);
+            ctx.set_line_width(1.5);
+            ctx.begin_path();
+            let mut first = true;
+            for (time, observables) in history.samples().filter(|(time, _)| *time >= start) {
+                let value = s.value(observables);
+                if !value.is_finite() {
+                    first = true;
+                    continue;
+                }
+                if first {
+                    ctx.move_to(x(*time), y(value));
+                    first = false;
+                } else {
+                    ctx.line_to(x(*time), y(value));
+                }
+            }
+            ctx.stroke();
+
+            // Legend
+            ctx.set_fill_style(&color);
+            let _ = ctx.fill_text(
+                s.label(),
+                MARGIN_LEFT + 5.,
+                MARGIN_TOP + 12. * (i + 1) as f64,
+            );
+        }
+    }
+}
+
+fn series_color(series: Series) -> &'static str {
+    match series {
+        Series::Energy => "#ffcc00",
+        Series::MomentumX => "#ff4444",
+        Series::MomentumY => "#44ff44",
+        Series::MomentumZ => "#4488ff",
+        Series::AngularMomentum => "#ff44ff",
+        Series::Temperature => "#44ffff",
+    }
+}
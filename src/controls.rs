@@ -1,62 +1,152 @@
-use crate::canvas::APP_DIV_ID;
+use crate::logging;
+use crate::simulation::comparison::Settings;
+use crate::simulation::dipole::Model;
+use crate::simulation::dipole::Objects;
+use crate::simulation::integrator::Integrator;
 use crate::App;
+use crate::CompareView;
+use crate::Follow;
+use crate::Motion;
 use crate::Msg;
-use nalgebra::Vector3;
+use crate::Preset;
+use crate::Projection;
+use crate::Series;
+use crate::Tool;
+use std::io::Cursor;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
 use web_sys::window;
+use web_sys::Blob;
 use web_sys::Element;
+use web_sys::FileReader;
+use web_sys::HtmlAnchorElement;
 use web_sys::HtmlElement;
 use web_sys::HtmlInputElement;
+use web_sys::HtmlSelectElement;
+use web_sys::Url;
 
-pub fn append_values(app: Rc<App>) -> Result<(), JsValue> {
-    let window = window().unwrap();
-    let document = window.document().unwrap();
-
-    let container: HtmlElement = match document.get_element_by_id(APP_DIV_ID) {
-        Some(container) => container.dyn_into().expect("Html element"),
-        None => document.body().expect("Document body"),
-    };
-
-    Ok(())
+/// The elements the observables are written to, kept so that they never have to be looked up
+/// in a document that may hold several clients
+pub struct Readouts {
+    angular_momentum: Element,
+    momentum: Element,
+    energy: Element,
+    energies: Vec<Element>,
+    drifts: Vec<Element>,
+    drift_warning: HtmlElement,
+    divergence: Element,
+    /// The buttons adding each kind of object, disabled when the simulation won't take it
+    add_buttons: Vec<(Objects, HtmlElement)>,
+    add_error: HtmlElement,
 }
 
-pub fn update_values(app: Rc<App>, l: Vector3<f64>, p: Vector3<f64>, e: f64) {
-    let window = window().unwrap();
-    let document = window.document().unwrap();
-
-    let angular_momentum = document.get_element_by_id("angular_momentum").unwrap();
-    let momentum = document.get_element_by_id("momentum").unwrap();
-    let energy = document.get_element_by_id("energy").unwrap();
+pub fn update_values(readouts: &Readouts, app: &App) {
+    let store = app.store.borrow();
+    let diagnostics = store.state.diagnostics();
+    let current = diagnostics.current();
+    let (l, p, e) = (current.angular_momentum, current.momentum, current.energy);
 
+    let Readouts {
+        angular_momentum,
+        momentum,
+        energy,
+        ..
+    } = readouts;
     angular_momentum.set_inner_html(&format!("({:.5}, {:.5}, {:.5})", l.x, l.y, l.z));
     momentum.set_inner_html(&format!("({:.5}, {:.5}, {:.5})", p.x, p.y, p.z));
     energy.set_inner_html(&format!("{:.5}", e));
+
+    let breakdown = current.energy_breakdown;
+    let energies = [
+        breakdown.translational,
+        breakdown.rotational,
+        breakdown.intra_dipole,
+        breakdown.inter_object,
+        breakdown.external,
+    ];
+    for (element, value) in readouts.energies.iter().zip(energies.iter()) {
+        element.set_inner_html(&format!("{:.5}", value));
+    }
+
+    let latest = diagnostics.latest();
+    let max = diagnostics.max();
+    let drifts = [
+        (latest.angular_momentum, max.angular_momentum),
+        (latest.momentum, max.momentum),
+        (latest.energy, max.energy),
+    ];
+    for (element, (drift, max_drift)) in readouts.drifts.iter().zip(drifts.iter()) {
+        element.set_inner_html(&format!(
+            "{:.3e} ({:.3}%, max {:.3}%)",
+            drift.absolute,
+            drift.relative * 100.,
+            max_drift.relative * 100.
+        ));
+    }
+
+    let divergence = match (store.state.comparison(), store.state.divergence()) {
+        (None, _) => "not comparing".to_string(),
+        (Some(_), None) => "different objects".to_string(),
+        (Some(_), Some(divergence)) => format!(
+            "position {:.3e} (max {:.3e}), orientation {:.3e} rad, energy {:.3e}",
+            divergence.position, divergence.max_position, divergence.orientation, divergence.energy
+        ),
+    };
+    readouts.divergence.set_inner_html(&divergence);
+
+    let drift_warning = &readouts.drift_warning;
+    let display = if diagnostics.exceeded() {
+        "block"
+    } else {
+        "none"
+    };
+    drift_warning
+        .style()
+        .set_property("display", display)
+        .unwrap();
+    if let Some(time) = diagnostics.exceeded_at() {
+        drift_warning.set_inner_html(&format!(
+            "Drift exceeded {}% at t = {:.3}",
+            diagnostics.threshold() * 100.,
+            time
+        ));
+    }
+
+    for (kind, button) in readouts.add_buttons.iter() {
+        match store.state.can_add(*kind) {
+            Ok(()) => {
+                button.remove_attribute("disabled").unwrap();
+                button.remove_attribute("title").unwrap();
+            }
+            Err(err) => {
+                button.set_attribute("disabled", "").unwrap();
+                button.set_attribute("title", &err).unwrap();
+            }
+        }
+    }
+
+    let add_error = &readouts.add_error;
+    match store.state.add_error() {
+        Some(err) => {
+            add_error.set_inner_html(&format!("Could not add object: {}", err));
+            add_error.style().set_property("display", "block").unwrap();
+        }
+        None => add_error.style().set_property("display", "none").unwrap(),
+    }
 }
 
-pub fn append_controls(app: Rc<App>) -> Result<(), JsValue> {
+pub fn append_controls(app: Rc<App>, container: &HtmlElement) -> Result<Readouts, JsValue> {
     let window = window().unwrap();
     let document = window.document().unwrap();
 
-    let container: HtmlElement = match document.get_element_by_id(APP_DIV_ID) {
-        Some(container) => container.dyn_into().expect("Html element"),
-        None => document.body().expect("Document body"),
-    };
-
     let controls = document.create_element("div")?;
     container.append_child(&controls)?;
     let controls: HtmlElement = controls.dyn_into()?;
     controls.style().set_property("padding-left", "5px")?;
     let controls: Element = controls.dyn_into()?;
 
-    // Render Scenery
-    /* {
-        let app = Rc::clone(&app);
-        let show_scenery_control = create_show_scenery_control(app)?;
-        controls.append_child(&show_scenery_control)?;
-    } */
     {
         let app = Rc::clone(&app);
         let time_scale_slider = create_time_scale_control(app)?;
@@ -72,29 +162,238 @@ pub fn append_controls(app: Rc<App>) -> Result<(), JsValue> {
         let reset = create_reset_button(app)?;
         controls.append_child(&reset)?;
     }
+    {
+        let app = Rc::clone(&app);
+        let pause = create_msg_button(app, "Pause", Msg::Paused(true))?;
+        controls.append_child(&pause)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let resume = create_msg_button(app, "Resume", Msg::Paused(false))?;
+        controls.append_child(&resume)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let step = create_msg_button(app, "Step", Msg::Step)?;
+        controls.append_child(&step)?;
+    }
+    let mut add_buttons = vec![];
+    {
+        let app = Rc::clone(&app);
+        let add_dipole =
+            create_msg_button(app, "Add dipole", Msg::Tool(Tool::Add(Objects::Dipole)))?;
+        controls.append_child(&add_dipole)?;
+        add_buttons.push((Objects::Dipole, add_dipole));
+    }
+    {
+        let app = Rc::clone(&app);
+        let add_charge =
+            create_msg_button(app, "Add charge", Msg::Tool(Tool::Add(Objects::Charge)))?;
+        controls.append_child(&add_charge)?;
+        add_buttons.push((Objects::Charge, add_charge));
+    }
+    {
+        let app = Rc::clone(&app);
+        let select = create_msg_button(app, "Select", Msg::Tool(Tool::Select))?;
+        controls.append_child(&select)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let velocity = create_msg_button(
+            app,
+            "Set velocity",
+            Msg::Tool(Tool::Slingshot(Motion::Linear)),
+        )?;
+        controls.append_child(&velocity)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let angular_velocity = create_msg_button(
+            app,
+            "Set angular velocity",
+            Msg::Tool(Tool::Slingshot(Motion::Angular)),
+        )?;
+        controls.append_child(&angular_velocity)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let duplicate = create_selection_button(app, "Duplicate", Msg::DuplicateObject)?;
+        controls.append_child(&duplicate)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let delete = create_selection_button(app, "Delete", Msg::DeleteObject)?;
+        controls.append_child(&delete)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let frame_all = create_msg_button(app, "Frame all", Msg::FrameAll)?;
+        controls.append_child(&frame_all)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let frame_selection = create_msg_button(app, "Frame selection", Msg::FrameSelection)?;
+        controls.append_child(&frame_selection)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let follow = create_follow_control(app)?;
+        controls.append_child(&follow)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let preset = create_preset_control(app)?;
+        controls.append_child(&preset)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let orthographic = create_orthographic_control(app)?;
+        controls.append_child(&orthographic)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let chart_window = create_chart_window_control(app)?;
+        controls.append_child(&chart_window)?;
+    }
+    for series in Series::ALL.iter() {
+        let app = Rc::clone(&app);
+        let shown = app.store.borrow().state.chart_series().contains(series);
+        let series_control = create_chart_series_control(app, *series, shown)?;
+        controls.append_child(&series_control)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let compare = create_compare_integrator_control(app)?;
+        controls.append_child(&compare)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let model = create_compare_model_control(app)?;
+        controls.append_child(&model)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let substeps = create_compare_substeps_control(app)?;
+        controls.append_child(&substeps)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let offset = create_compare_offset_control(app)?;
+        controls.append_child(&offset)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let ghost = create_compare_ghost_control(app)?;
+        controls.append_child(&ghost)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let record = create_record_trajectory_control(app)?;
+        controls.append_child(&record)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let stride = create_export_stride_control(app)?;
+        controls.append_child(&stride)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let download_xyz = create_download_button(app, ExportFormat::ExtendedXyz)?;
+        controls.append_child(&download_xyz)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let download_csv = create_download_button(app, ExportFormat::Csv)?;
+        controls.append_child(&download_csv)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let download_binary = create_download_button(app, ExportFormat::Binary)?;
+        controls.append_child(&download_binary)?;
+    }
+    {
+        let app = Rc::clone(&app);
+        let load_trajectory = create_load_trajectory_control(app)?;
+        controls.append_child(&load_trajectory)?;
+    }
     //append readouts
     let angular_momentum_labal = document.create_element("p")?;
     angular_momentum_labal.set_inner_html("Angular Momentum: ");
     let angular_momentum = document.create_element("span")?;
-    angular_momentum.set_id("angular_momentum");
     angular_momentum_labal.append_child(&angular_momentum)?;
     controls.append_child(&angular_momentum_labal)?;
 
     let momentum = document.create_element("p")?;
     momentum.set_inner_html("Momentum: ");
     let momentum_value = document.create_element("span")?;
-    momentum_value.set_id("momentum");
     momentum.append_child(&momentum_value)?;
     controls.append_child(&momentum)?;
 
     let energy = document.create_element("p")?;
     energy.set_inner_html("Energy: ");
     let energy_value = document.create_element("span")?;
-    energy_value.set_id("energy");
     energy.append_child(&energy_value)?;
     controls.append_child(&energy)?;
 
-    Ok(())
+    let energy_labels = [
+        "Translational KE: ",
+        "Rotational KE: ",
+        "Intra-dipole PE: ",
+        "Inter-object PE: ",
+        "External field PE: ",
+    ];
+    let mut energies = vec![];
+    for label in energy_labels.iter() {
+        let energy = document.create_element("p")?;
+        energy.set_inner_html(label);
+        let energy_value = document.create_element("span")?;
+        energy.append_child(&energy_value)?;
+        controls.append_child(&energy)?;
+        energies.push(energy_value);
+    }
+
+    let drift_labels = [
+        "Angular Momentum drift: ",
+        "Momentum drift: ",
+        "Energy drift: ",
+    ];
+    let mut drifts = vec![];
+    for label in drift_labels.iter() {
+        let drift = document.create_element("p")?;
+        drift.set_inner_html(label);
+        let drift_value = document.create_element("span")?;
+        drift.append_child(&drift_value)?;
+        controls.append_child(&drift)?;
+        drifts.push(drift_value);
+    }
+
+    let divergence_label = document.create_element("p")?;
+    divergence_label.set_inner_html("Divergence: ");
+    let divergence = document.create_element("span")?;
+    divergence_label.append_child(&divergence)?;
+    controls.append_child(&divergence_label)?;
+
+    let drift_warning: HtmlElement = document.create_element("p")?.dyn_into()?;
+    drift_warning.style().set_property("color", "red")?;
+    drift_warning.style().set_property("display", "none")?;
+    controls.append_child(&drift_warning)?;
+
+    let add_error: HtmlElement = document.create_element("p")?.dyn_into()?;
+    add_error.style().set_property("color", "red")?;
+    add_error.style().set_property("display", "none")?;
+    controls.append_child(&add_error)?;
+
+    Ok(Readouts {
+        angular_momentum,
+        momentum: momentum_value,
+        energy: energy_value,
+        energies,
+        drifts,
+        drift_warning,
+        divergence,
+        add_buttons,
+        add_error,
+    })
 }
 fn create_reset_button(app: Rc<App>) -> Result<HtmlElement, JsValue> {
     let handler = move |_event: web_sys::Event| {
@@ -110,23 +409,459 @@ fn create_reset_button(app: Rc<App>) -> Result<HtmlElement, JsValue> {
 
     Ok(reset_button)
 }
-fn create_show_scenery_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+/// A button that sends the same message every time it is clicked
+fn create_msg_button(app: Rc<App>, label: &'static str, msg: Msg) -> Result<HtmlElement, JsValue> {
+    let handler = move |_event: web_sys::Event| {
+        app.store.borrow_mut().msg(&msg);
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let button = Button { label, closure }.create_element()?;
+
+    Ok(button)
+}
+/// A button that sends a message about the selected object, and does nothing without one
+fn create_selection_button(
+    app: Rc<App>,
+    label: &'static str,
+    msg: fn(usize) -> Msg,
+) -> Result<HtmlElement, JsValue> {
+    let handler = move |_event: web_sys::Event| {
+        let selected = app.store.borrow().state.selected();
+        if let Some(index) = selected {
+            app.store.borrow_mut().msg(&msg(index));
+        }
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let button = Button { label, closure }.create_element()?;
+
+    Ok(button)
+}
+
+fn create_follow_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let select_elem: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+        let follow = match select_elem.value().as_str() {
+            "center-of-mass" => Follow::CenterOfMass,
+            "selected" => Follow::Selected,
+            _ => Follow::Free,
+        };
+        app.store.borrow_mut().msg(&Msg::Follow(follow));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let follow_select = Select {
+        options: vec![
+            ("free", "Nothing"),
+            ("center-of-mass", "Centre of mass"),
+            ("selected", "Selected object"),
+        ],
+        selected: "free",
+        label: "Camera follows",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(follow_select)
+}
+
+fn create_preset_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let select_elem: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+        let value = select_elem.value();
+        if let Some(preset) = Preset::ALL.iter().find(|preset| preset.name() == value) {
+            app.store.borrow_mut().msg(&Msg::LookFrom(*preset));
+        }
+        // Going back to the prompt lets the same view be picked again after orbiting away
+        select_elem.set_value("");
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let mut options = vec![("", "Choose a view")];
+    options.extend(
+        Preset::ALL
+            .iter()
+            .map(|preset| (preset.name(), preset.label())),
+    );
+    let preset_select = Select {
+        options,
+        selected: "",
+        label: "Look from",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(preset_select)
+}
+
+fn create_orthographic_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let mode = if input_elem.checked() {
+            Projection::Orthographic
+        } else {
+            Projection::Perspective
+        };
+
+        app.store.borrow_mut().msg(&Msg::Projection(mode));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let orthographic_control = Checkbox {
+        start_checked: false,
+        label: "Flat projection",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(orthographic_control)
+}
+
+fn create_chart_window_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let start = app.store.borrow().state.history().window() as f32;
     let handler = move |event: web_sys::Event| {
         let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
-        let show_scenery = input_elem.checked();
+        let window = input_elem.value_as_number();
 
-        app.store.borrow_mut().msg(&Msg::ShowScenery(show_scenery));
+        app.store.borrow_mut().msg(&Msg::ChartWindow(window as f32));
     };
     let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
 
-    let show_scenery_control = Checkbox {
-        start_checked: true,
-        label: "Show Scenery",
+    let window_slider = Slider {
+        start,
+        min: 1.0,
+        max: 60.0,
+        step: 1.0,
+        label: "Chart window (s)",
         closure,
     }
     .create_element()?;
 
-    Ok(show_scenery_control)
+    Ok(window_slider)
+}
+
+fn create_chart_series_control(
+    app: Rc<App>,
+    series: Series,
+    start_checked: bool,
+) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let shown = input_elem.checked();
+
+        app.store.borrow_mut().msg(&Msg::ChartSeries(series, shown));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let series_control = Checkbox {
+        start_checked,
+        label: series.label(),
+        closure,
+    }
+    .create_element()?;
+
+    Ok(series_control)
+}
+
+/// Restart the comparison with one of its settings changed, starting it if need be
+fn change_comparison(app: &App, change: impl FnOnce(&mut Settings)) {
+    let mut settings = app
+        .store
+        .borrow()
+        .state
+        .comparison()
+        .map_or_else(Settings::default, |comparison| *comparison.settings());
+    change(&mut settings);
+    app.store.borrow_mut().msg(&Msg::Compare(Some(settings)));
+}
+
+fn create_compare_integrator_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let select_elem: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+        match select_elem.value().parse::<Integrator>() {
+            Ok(integrator) => change_comparison(&app, |settings| settings.integrator = integrator),
+            Err(_) => app.store.borrow_mut().msg(&Msg::Compare(None)),
+        }
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let mut options = vec![("off", "Off")];
+    options.extend(
+        Integrator::ALL
+            .iter()
+            .map(|integrator| (integrator.name(), integrator.name())),
+    );
+    let compare_select = Select {
+        options,
+        selected: "off",
+        label: "Compare with integrator",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(compare_select)
+}
+
+fn create_compare_model_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let select_elem: HtmlSelectElement = event.target().unwrap().dyn_into().unwrap();
+        let value = select_elem.value();
+        if let Some(model) = Model::ALL.iter().find(|model| model.name() == value) {
+            change_comparison(&app, |settings| settings.model = *model);
+        }
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let model_select = Select {
+        options: vec![
+            (Model::Finite.name(), "Two charges"),
+            (Model::Point.name(), "Point dipoles"),
+        ],
+        selected: Model::default().name(),
+        label: "Comparison dipole model",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(model_select)
+}
+
+fn create_compare_substeps_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let substeps = input_elem.value_as_number() as u32;
+
+        change_comparison(&app, |settings| settings.substeps = substeps);
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let substeps_slider = Slider {
+        start: 1.0,
+        min: 1.0,
+        max: 20.0,
+        step: 1.0,
+        label: "Comparison substeps",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(substeps_slider)
+}
+
+fn create_compare_offset_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let offset = input_elem.value_as_number();
+
+        change_comparison(&app, |settings| settings.offset = Some(offset));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let offset_slider = Slider {
+        start: 1.0,
+        min: 0.1,
+        max: 10.0,
+        step: 0.1,
+        label: "Comparison dipole offset",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(offset_slider)
+}
+
+fn create_compare_ghost_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let view = if input_elem.checked() {
+            CompareView::Ghost
+        } else {
+            CompareView::Split
+        };
+
+        app.store.borrow_mut().msg(&Msg::CompareView(view));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let ghost_control = Checkbox {
+        start_checked: false,
+        label: "Overlay comparison",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(ghost_control)
+}
+
+fn create_record_trajectory_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let record = input_elem.checked();
+
+        app.store.borrow_mut().msg(&Msg::RecordTrajectory(record));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let record_control = Checkbox {
+        start_checked: false,
+        label: "Record trajectory",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(record_control)
+}
+
+fn create_export_stride_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let stride = input_elem.value_as_number();
+
+        app.store
+            .borrow_mut()
+            .msg(&Msg::ExportStride(stride as u32));
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let stride_slider = Slider {
+        start: 1.0,
+        min: 1.0,
+        max: 60.0,
+        step: 1.0,
+        label: "Export stride",
+        closure,
+    }
+    .create_element()?;
+
+    Ok(stride_slider)
+}
+
+enum ExportFormat {
+    ExtendedXyz,
+    Csv,
+    Binary,
+}
+
+fn create_download_button(app: Rc<App>, format: ExportFormat) -> Result<HtmlElement, JsValue> {
+    let label = match format {
+        ExportFormat::ExtendedXyz => "Download XYZ",
+        ExportFormat::Csv => "Download CSV",
+        ExportFormat::Binary => "Download binary",
+    };
+    let handler = move |_event: web_sys::Event| {
+        let store = app.store.borrow();
+        let trajectory = store.state.trajectory();
+
+        let mut contents = Vec::new();
+        let (filename, result) = match format {
+            ExportFormat::ExtendedXyz => (
+                "trajectory.xyz",
+                trajectory.write_extended_xyz(&mut contents),
+            ),
+            ExportFormat::Csv => ("trajectory.csv", trajectory.write_csv(&mut contents)),
+            ExportFormat::Binary => (
+                "trajectory.dptraj",
+                trajectory
+                    .write_binary(Cursor::new(&mut contents), store.state.integrator().name())
+                    .map(|_| ()),
+            ),
+        };
+        if let Err(err) = result {
+            logging::warn(&format!("Could not write trajectory: {}", err));
+            return;
+        }
+
+        if let Err(err) = download(filename, &contents) {
+            logging::warn(&format!("Could not download trajectory: {:?}", err));
+        }
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let download_button = Button { label, closure }.create_element()?;
+
+    Ok(download_button)
+}
+
+/// A file picker that replaces the running simulation with playback of a binary trajectory
+fn create_load_trajectory_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
+    let window = window().unwrap();
+    let document = window.document().unwrap();
+
+    // One reader and one onload handler serve every file chosen
+    let reader = FileReader::new()?;
+    let onload = {
+        let reader = reader.clone();
+        let app = Rc::clone(&app);
+        move |_event: web_sys::Event| {
+            let bytes = js_sys::Uint8Array::new(&reader.result().unwrap()).to_vec();
+            app.store.borrow_mut().msg(&Msg::LoadTrajectory(bytes));
+        }
+    };
+    let onload = Closure::wrap(Box::new(onload) as Box<dyn FnMut(_)>);
+    reader.set_onload(Some(onload.as_ref().unchecked_ref()));
+    onload.forget();
+
+    let handler = move |event: web_sys::Event| {
+        let input_elem: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+        let file = match input_elem.files().and_then(|files| files.get(0)) {
+            Some(file) => file,
+            None => return,
+        };
+
+        // A file still being read is dropped for the newly chosen one
+        reader.abort();
+        if let Err(err) = reader.read_as_array_buffer(&file) {
+            logging::warn(&format!("Could not read trajectory: {:?}", err));
+        }
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+
+    let input: HtmlInputElement = document.create_element("input")?.dyn_into()?;
+    input.set_type("file");
+    input.set_accept(".dptraj");
+    input.set_onchange(Some(closure.as_ref().unchecked_ref()));
+    closure.forget();
+
+    let label = document.create_element("div")?;
+    label.set_inner_html("Play trajectory");
+
+    let leave = create_msg_button(app, "Back to simulation", Msg::LeavePlayback)?;
+
+    let container = document.create_element("div")?;
+    container.append_child(&label)?;
+    container.append_child(&input)?;
+    container.append_child(&leave)?;
+
+    let container: HtmlElement = container.dyn_into()?;
+    container.style().set_property("margin-bottom", "15px")?;
+
+    Ok(container)
+}
+
+/// Hand a file to the browser to save by clicking a temporary link to a blob of its contents
+fn download(filename: &str, contents: &[u8]) -> Result<(), JsValue> {
+    let window = window().unwrap();
+    let document = window.document().unwrap();
+
+    let parts = js_sys::Array::new();
+    parts.push(&js_sys::Uint8Array::from(contents));
+    let blob = Blob::new_with_u8_array_sequence(&parts)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let link: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    link.set_href(&url);
+    link.set_download(filename);
+    link.click();
+
+    // Revoked a while later, as some browsers cancel a download whose URL is revoked straight
+    // after the click
+    let revoke = Closure::once_into_js(move || {
+        let _ = Url::revoke_object_url(&url);
+    });
+    window.set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), 10_000)?;
+
+    Ok(())
 }
 
 fn create_time_scale_control(app: Rc<App>) -> Result<HtmlElement, JsValue> {
@@ -270,3 +1005,42 @@ impl Slider {
         Ok(container)
     }
 }
+
+struct Select {
+    options: Vec<(&'static str, &'static str)>,
+    selected: &'static str,
+    label: &'static str,
+    closure: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl Select {
+    fn create_element(self) -> Result<HtmlElement, JsValue> {
+        let window = window().unwrap();
+        let document = window.document().unwrap();
+
+        let select: HtmlSelectElement = document.create_element("select")?.dyn_into()?;
+        for (value, label) in self.options.iter() {
+            let option = document.create_element("option")?;
+            option.set_attribute("value", value)?;
+            option.set_inner_html(label);
+            select.append_child(&option)?;
+        }
+        select.set_value(self.selected);
+
+        let closure = self.closure;
+        select.set_onchange(Some(closure.as_ref().unchecked_ref()));
+        closure.forget();
+
+        let label = document.create_element("div")?;
+        label.set_inner_html(self.label);
+
+        let container = document.create_element("div")?;
+        container.append_child(&label)?;
+        container.append_child(&select)?;
+
+        let container: HtmlElement = container.dyn_into()?;
+        container.style().set_property("margin-bottom", "15px")?;
+
+        Ok(container)
+    }
+}
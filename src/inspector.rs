@@ -0,0 +1,259 @@
+//! A panel showing the values of the selected object, which can be typed over to change them
+//! while the simulation runs
+
+use crate::simulation::dipole::{Edit, Object, Objects};
+use crate::App;
+use crate::Msg;
+use nalgebra::Vector3;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::window;
+use web_sys::Element;
+use web_sys::HtmlElement;
+use web_sys::HtmlInputElement;
+
+/// The values of an object the inspector can change
+#[derive(Clone, Copy)]
+enum Property {
+    Mass,
+    Charge,
+    Offset,
+    Position,
+    Velocity,
+    Orientation,
+    AngularVelocity,
+}
+
+impl Property {
+    const ALL: [Property; 7] = [
+        Property::Mass,
+        Property::Charge,
+        Property::Offset,
+        Property::Position,
+        Property::Velocity,
+        Property::Orientation,
+        Property::AngularVelocity,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Property::Mass => "Mass",
+            Property::Charge => "Charge",
+            Property::Offset => "Offset",
+            Property::Position => "Position",
+            Property::Velocity => "Velocity",
+            Property::Orientation => "Orientation",
+            Property::AngularVelocity => "Angular velocity",
+        }
+    }
+
+    /// Point charges have no orientation or size
+    fn dipole_only(self) -> bool {
+        match self {
+            Property::Offset | Property::Orientation | Property::AngularVelocity => true,
+            Property::Mass | Property::Charge | Property::Position | Property::Velocity => false,
+        }
+    }
+
+    /// How many numbers the property is made of
+    fn components(self) -> usize {
+        match self {
+            Property::Mass | Property::Charge | Property::Offset => 1,
+            Property::Position
+            | Property::Velocity
+            | Property::Orientation
+            | Property::AngularVelocity => 3,
+        }
+    }
+
+    /// The value of this property of `object`, as one number or the three components of a vector
+    fn value(self, object: &dyn Object) -> Vec<f64> {
+        let vector = |v: Vector3<f64>| vec![v.x, v.y, v.z];
+        match self {
+            Property::Mass => vec![object.get_mass()],
+            Property::Charge => vec![object.get_charge()],
+            Property::Offset => vec![object.get_offset()],
+            Property::Position => vector(object.get_pos()),
+            Property::Velocity => vector(object.get_velocity()),
+            Property::Orientation => vector(object.get_orientation()),
+            Property::AngularVelocity => vector(object.get_angular_velocity()),
+        }
+    }
+
+    /// The edit setting this property to `values`, laid out as by `value`
+    fn edit(self, values: &[f64]) -> Edit {
+        let vector = || Vector3::new(values[0], values[1], values[2]);
+        match self {
+            Property::Mass => Edit::Mass(values[0]),
+            Property::Charge => Edit::Charge(values[0]),
+            Property::Offset => Edit::Offset(values[0]),
+            Property::Position => Edit::Position(vector()),
+            Property::Velocity => Edit::Velocity(vector()),
+            Property::Orientation => Edit::Orientation(vector()),
+            Property::AngularVelocity => Edit::AngularVelocity(vector()),
+        }
+    }
+}
+
+/// A row of the inspector: a label and one input per component of the property
+struct Field {
+    property: Property,
+    row: HtmlElement,
+    inputs: Vec<HtmlInputElement>,
+}
+
+/// Shows the selected object and sends `Msg::EditObject` when one of its values is changed
+pub struct Inspector {
+    panel: HtmlElement,
+    title: Element,
+    fields: Vec<Field>,
+    force: Element,
+    torque: Element,
+    torque_row: HtmlElement,
+}
+
+impl Inspector {
+    pub fn new(app: Rc<App>, container: &HtmlElement) -> Result<Inspector, JsValue> {
+        let window = window().unwrap();
+        let document = window.document().unwrap();
+
+        let panel: HtmlElement = document.create_element("div")?.dyn_into()?;
+        panel.style().set_property("padding-left", "5px")?;
+        panel.style().set_property("display", "none")?;
+        container.append_child(&panel)?;
+
+        let title = document.create_element("h4")?;
+        panel.append_child(&title)?;
+
+        let mut fields = vec![];
+        for property in Property::ALL.iter() {
+            let app = Rc::clone(&app);
+            let field = create_field(app, *property)?;
+            panel.append_child(&field.row)?;
+            fields.push(field);
+        }
+
+        let force_row = document.create_element("p")?;
+        force_row.set_inner_html("Force: ");
+        let force = document.create_element("span")?;
+        force_row.append_child(&force)?;
+        panel.append_child(&force_row)?;
+
+        let torque_row: HtmlElement = document.create_element("p")?.dyn_into()?;
+        torque_row.set_inner_html("Torque: ");
+        let torque = document.create_element("span")?;
+        torque_row.append_child(&torque)?;
+        panel.append_child(&torque_row)?;
+
+        Ok(Inspector {
+            panel,
+            title,
+            fields,
+            force,
+            torque,
+            torque_row,
+        })
+    }
+
+    /// Show the current values of the selected object, or hide the panel if there is none
+    pub fn update(&self, app: &App) {
+        let store = app.store.borrow();
+        let simulation = store.state.simulation();
+        let objects = simulation.get_objects();
+        let (index, object) = match store.state.selected() {
+            Some(index) if index < objects.len() => (index, objects[index]),
+            _ => {
+                show(&self.panel, false);
+                return;
+            }
+        };
+        show(&self.panel, true);
+
+        let is_dipole = object.get_type() == Objects::Dipole;
+        let kind = if is_dipole { "Dipole" } else { "Charge" };
+        self.title.set_inner_html(&format!("{} {}", kind, index));
+
+        // Leave alone the input being typed in, so the value isn't replaced under the cursor
+        let focused = window().unwrap().document().unwrap().active_element();
+        for field in self.fields.iter() {
+            let shown = is_dipole || !field.property.dipole_only();
+            show(&field.row, shown);
+            if !shown {
+                continue;
+            }
+            for (input, value) in field.inputs.iter().zip(field.property.value(object)) {
+                let element: &Element = input;
+                if focused.as_ref() != Some(element) {
+                    input.set_value(&format!("{:.4}", value));
+                }
+            }
+        }
+
+        let (force, torque) = simulation
+            .get_forces()
+            .get(index)
+            .copied()
+            .unwrap_or_default();
+        self.force
+            .set_inner_html(&format!("({:.5}, {:.5}, {:.5})", force.x, force.y, force.z));
+        self.torque.set_inner_html(&format!(
+            "({:.5}, {:.5}, {:.5})",
+            torque.x, torque.y, torque.z
+        ));
+        show(&self.torque_row, is_dipole);
+    }
+}
+
+fn show(element: &HtmlElement, shown: bool) {
+    let display = if shown { "block" } else { "none" };
+    element.style().set_property("display", display).unwrap();
+}
+
+/// A labelled row of number inputs that edits `property` of the selected object when any of
+/// them is changed
+fn create_field(app: Rc<App>, property: Property) -> Result<Field, JsValue> {
+    let window = window().unwrap();
+    let document = window.document().unwrap();
+
+    let row: HtmlElement = document.create_element("div")?.dyn_into()?;
+    row.style().set_property("margin-bottom", "5px")?;
+    let label = document.create_element("div")?;
+    label.set_inner_html(property.label());
+    row.append_child(&label)?;
+
+    let mut inputs = vec![];
+    for _ in 0..property.components() {
+        let input: HtmlInputElement = document.create_element("input")?.dyn_into()?;
+        input.set_type("number");
+        input.set_step("any");
+        input.style().set_property("width", "6em")?;
+        row.append_child(&input)?;
+        inputs.push(input);
+    }
+
+    let row_inputs = inputs.clone();
+    let handler = move |_event: web_sys::Event| {
+        let values: Vec<f64> = row_inputs.iter().map(|i| i.value_as_number()).collect();
+        if values.iter().any(|value| value.is_nan()) {
+            return;
+        }
+        let selected = app.store.borrow().state.selected();
+        if let Some(index) = selected {
+            let edit = property.edit(&values);
+            app.store.borrow_mut().msg(&Msg::EditObject(index, edit));
+        }
+    };
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(_)>);
+    for input in inputs.iter() {
+        input.set_onchange(Some(closure.as_ref().unchecked_ref()));
+    }
+    closure.forget();
+
+    Ok(Field {
+        property,
+        row,
+        inputs,
+    })
+}
@@ -0,0 +1,83 @@
+//! Text drawn over the canvas beside things in the scene
+
+use crate::app::Motion;
+use crate::app::State;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::window;
+use web_sys::HtmlElement;
+
+/// Labels the arrows of the slingshot tool with the speed they stand for
+pub struct Labels {
+    layer: HtmlElement,
+    labels: Vec<HtmlElement>,
+}
+
+impl Labels {
+    /// Put a layer for the labels over everything in `stage`
+    pub fn new(stage: &HtmlElement) -> Result<Labels, JsValue> {
+        let window = window().unwrap();
+        let document = window.document().unwrap();
+
+        let layer: HtmlElement = document.create_element("div")?.dyn_into()?;
+        let style = layer.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("left", "0")?;
+        style.set_property("top", "0")?;
+        style.set_property("pointer-events", "none")?;
+        style.set_property("color", "white")?;
+        style.set_property("font", "11px sans-serif")?;
+        stage.append_child(&layer)?;
+
+        Ok(Labels {
+            layer,
+            labels: vec![],
+        })
+    }
+
+    /// Show a label at the tip of every arrow, hiding the labels left over
+    pub fn draw(&mut self, state: &State) {
+        let texts: Vec<_> = state
+            .arrows()
+            .iter()
+            .filter(|arrow| arrow.value.norm() > f64::EPSILON)
+            .filter_map(|arrow| {
+                let (x, y) = state.canvas_position(arrow.end())?;
+                let symbol = match arrow.motion {
+                    Motion::Linear => "v",
+                    Motion::Angular => "ω",
+                };
+                Some((x, y, format!("|{}| = {:.3}", symbol, arrow.value.norm())))
+            })
+            .collect();
+
+        while self.labels.len() < texts.len() {
+            match self.create_label() {
+                Ok(label) => self.labels.push(label),
+                Err(_) => return,
+            }
+        }
+
+        for (index, label) in self.labels.iter().enumerate() {
+            let style = label.style();
+            match texts.get(index) {
+                Some((x, y, text)) => {
+                    label.set_inner_html(text);
+                    style.set_property("left", &format!("{}px", x)).unwrap();
+                    style.set_property("top", &format!("{}px", y)).unwrap();
+                    style.set_property("display", "block").unwrap();
+                }
+                None => style.set_property("display", "none").unwrap(),
+            }
+        }
+    }
+
+    fn create_label(&self) -> Result<HtmlElement, JsValue> {
+        let document = window().unwrap().document().unwrap();
+        let label: HtmlElement = document.create_element("div")?.dyn_into()?;
+        label.style().set_property("position", "absolute")?;
+        label.style().set_property("white-space", "nowrap")?;
+        self.layer.append_child(&label)?;
+        Ok(label)
+    }
+}
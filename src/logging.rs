@@ -0,0 +1,108 @@
+//! Logging that works both in the browser and natively.
+//!
+//! The simulation core never talks to `web_sys` directly. Messages go through the logger installed
+//! for the current thread, which by default writes to the browser console when the `web` feature
+//! is enabled in a wasm build and to stderr otherwise.
+
+use std::cell::RefCell;
+
+/// How important a log message is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Tracing output that is only interesting while debugging, such as per-step values
+    Debug,
+    /// Normal progress messages
+    Info,
+    /// Something went wrong but the application can carry on
+    Warn,
+}
+
+/// Somewhere to send log messages
+pub trait Logger {
+    /// Record a message
+    fn log(&self, level: Level, message: &str);
+}
+
+/// Writes messages at or above `min_level` to the browser console
+#[cfg(feature = "web")]
+pub struct ConsoleLogger {
+    /// Messages below this level are dropped
+    pub min_level: Level,
+}
+
+#[cfg(feature = "web")]
+impl Logger for ConsoleLogger {
+    fn log(&self, level: Level, message: &str) {
+        if level < self.min_level {
+            return;
+        }
+        match level {
+            Level::Warn => web_sys::console::warn_1(&message.into()),
+            _ => web_sys::console::log_1(&message.into()),
+        }
+    }
+}
+
+/// Writes messages at or above `min_level` to stderr
+pub struct StderrLogger {
+    /// Messages below this level are dropped
+    pub min_level: Level,
+}
+
+impl Logger for StderrLogger {
+    fn log(&self, level: Level, message: &str) {
+        if level >= self.min_level {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+/// Drops every message
+pub struct NullLogger;
+
+impl Logger for NullLogger {
+    fn log(&self, _level: Level, _message: &str) {}
+}
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+fn default_logger() -> Box<dyn Logger> {
+    Box::new(ConsoleLogger {
+        min_level: Level::Info,
+    })
+}
+
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+fn default_logger() -> Box<dyn Logger> {
+    Box::new(StderrLogger {
+        min_level: Level::Info,
+    })
+}
+
+thread_local! {
+    static LOGGER: RefCell<Box<dyn Logger>> = RefCell::new(default_logger());
+}
+
+/// Replace the logger used by the current thread
+pub fn set_logger(logger: Box<dyn Logger>) {
+    LOGGER.with(|current| *current.borrow_mut() = logger);
+}
+
+/// Send a message to the current thread's logger
+pub fn log(level: Level, message: &str) {
+    LOGGER.with(|logger| logger.borrow().log(level, message));
+}
+
+/// Log a message at `Level::Debug`
+pub fn debug(message: &str) {
+    log(Level::Debug, message);
+}
+
+/// Log a message at `Level::Info`
+pub fn info(message: &str) {
+    log(Level::Info, message);
+}
+
+/// Log a message at `Level::Warn`
+pub fn warn(message: &str) {
+    log(Level::Warn, message);
+}
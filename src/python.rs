@@ -0,0 +1,360 @@
+//! Python bindings for the simulation core, for scripting experiments from notebooks.
+//!
+//! ```python
+//! import dipole_simulation as ds
+//!
+//! sim = ds.Simulation({"objects": [...]})  # or a JSON string, or None for the default dipoles
+//! sim.integrator = "verlet"
+//! run = sim.run(dt=0.001, steps=10000, stride=100)
+//! run["positions"].shape  # (samples, objects, 3)
+//! ```
+
+use crate::simulation::batch::{self, Length, RunConfig};
+use crate::simulation::diagnostics::Observables;
+use crate::simulation::dipole::Object;
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use crate::simulation::integrator::Integrator;
+use crate::simulation::scenario::Scenario;
+use nalgebra::Vector3;
+use numpy::ndarray::{Array1, Array2, Array3};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArrayDyn};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+
+/// Position, orientation, velocity and angular velocity of one object
+type ObjectState = [Vector3<f64>; 4];
+
+/// A running simulation of dipoles or point charges
+#[pyclass(unsendable)]
+struct Simulation {
+    simulation: Box<dyn Simulatable>,
+    integrator: Integrator,
+    time: f64,
+}
+
+#[pymethods]
+impl Simulation {
+    /// Start from a scenario, given as a JSON string or anything `json.dumps` accepts, such as a
+    /// dict. Without one the two dipoles of the web client are simulated.
+    #[new]
+    #[pyo3(signature = (scenario=None))]
+    fn new(py: Python, scenario: Option<&Bound<PyAny>>) -> PyResult<Simulation> {
+        let scenario = match scenario {
+            None => Scenario::two_dipoles(),
+            Some(scenario) => {
+                let json = match scenario.downcast::<PyString>() {
+                    Ok(json) => json.to_cow()?.into_owned(),
+                    Err(_) => py
+                        .import("json")?
+                        .call_method1("dumps", (scenario,))?
+                        .extract()?,
+                };
+                Scenario::from_json(&json).map_err(PyValueError::new_err)?
+            }
+        };
+
+        Ok(Simulation {
+            simulation: scenario.build().map_err(PyValueError::new_err)?,
+            integrator: Integrator::default(),
+            time: 0.,
+        })
+    }
+
+    /// The current state as a scenario JSON string, which can be used to start a new simulation
+    fn to_json(&self) -> String {
+        Scenario::capture(self.simulation.as_ref(), "").to_json()
+    }
+
+    /// Name of the integrator: "euler", "verlet" or "rk4"
+    #[getter]
+    fn get_integrator(&self) -> &'static str {
+        self.integrator.name()
+    }
+
+    #[setter]
+    fn set_integrator(&mut self, name: &str) -> PyResult<()> {
+        self.integrator = name.parse().map_err(PyValueError::new_err)?;
+        self.simulation.set_integrator(self.integrator);
+        Ok(())
+    }
+
+    /// The uniform field applied on top of the field of the objects
+    #[getter]
+    fn get_external_field<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        vector(py, self.simulation.get_external_field())
+    }
+
+    #[setter]
+    fn set_external_field(&mut self, field: [f64; 3]) {
+        self.simulation.set_external_field(field.into());
+    }
+
+    /// Set the distance from the centre to each charge of every dipole
+    fn set_offset(&mut self, offset: f64) {
+        self.simulation.set_offset(offset);
+    }
+
+    /// Simulated time since the simulation was created or reset
+    #[getter]
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Advance by `steps` steps of `dt`
+    #[pyo3(signature = (dt, steps=1))]
+    fn step(&mut self, dt: f64, steps: u64) {
+        for _ in 0..steps {
+            self.simulation.update(dt);
+            self.time += dt;
+        }
+    }
+
+    /// Return every object to its initial state
+    fn reset(&mut self) {
+        self.simulation.reset();
+        self.time = 0.;
+    }
+
+    /// "dipole" or "charge" for every object
+    #[getter]
+    fn kinds(&self) -> Vec<&'static str> {
+        self.simulation
+            .get_objects()
+            .iter()
+            .map(|object| match object.get_type() {
+                Objects::Dipole => "dipole",
+                Objects::Charge => "charge",
+            })
+            .collect()
+    }
+
+    /// Mass of every object, shape (n,)
+    #[getter]
+    fn masses<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.scalars(py, |object| object.get_mass())
+    }
+
+    /// Charge of every object, shape (n,)
+    #[getter]
+    fn charges<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.scalars(py, |object| object.get_charge())
+    }
+
+    /// Offset of every object, zero for point charges, shape (n,)
+    #[getter]
+    fn offsets<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.scalars(py, |object| object.get_offset())
+    }
+
+    /// Position of every object, shape (n, 3)
+    #[getter]
+    fn positions<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        self.vectors(py, |object| object.get_pos())
+    }
+
+    /// Orientation of every object, zero for point charges, shape (n, 3)
+    #[getter]
+    fn orientations<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        self.vectors(py, |object| object.get_orientation())
+    }
+
+    /// Velocity of every object, shape (n, 3)
+    #[getter]
+    fn velocities<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        self.vectors(py, |object| object.get_velocity())
+    }
+
+    /// Angular velocity of every object, shape (n, 3)
+    #[getter]
+    fn angular_velocities<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        self.vectors(py, |object| object.get_angular_velocity())
+    }
+
+    /// The electric field at one point, shape (3,), or at many, shape (m, 3)
+    fn get_field<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyReadonlyArrayDyn<'py, f64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let points = points.as_array();
+        match points.shape() {
+            [3] => Ok(vector(py, self.simulation.get_field(point(points.iter()))).into_any()),
+            [m, 3] => {
+                let mut field = Array2::zeros((*m, 3));
+                for (i, row) in points.outer_iter().enumerate() {
+                    let value = self.simulation.get_field(point(row.iter()));
+                    field
+                        .row_mut(i)
+                        .assign(&Array1::from(vec![value.x, value.y, value.z]));
+                }
+                Ok(field.into_pyarray(py).into_any())
+            }
+            shape => Err(PyValueError::new_err(format!(
+                "points should have shape (3,) or (m, 3), not {:?}",
+                shape
+            ))),
+        }
+    }
+
+    /// Total energy
+    fn energy(&self) -> f64 {
+        self.simulation.get_total_energy()
+    }
+
+    /// Total energy split into translational, rotational, intra_dipole, inter_object and
+    /// external parts
+    fn energy_breakdown<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let breakdown = self.simulation.get_energy_breakdown();
+        let dict = PyDict::new(py);
+        dict.set_item("translational", breakdown.translational)?;
+        dict.set_item("rotational", breakdown.rotational)?;
+        dict.set_item("intra_dipole", breakdown.intra_dipole)?;
+        dict.set_item("inter_object", breakdown.inter_object)?;
+        dict.set_item("external", breakdown.external)?;
+        Ok(dict)
+    }
+
+    /// Total linear momentum, shape (3,)
+    fn momentum<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        vector(py, self.simulation.get_total_momentum())
+    }
+
+    /// Total angular momentum about the origin, shape (3,)
+    fn angular_momentum<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        vector(py, self.simulation.get_total_angular_momentum())
+    }
+
+    /// Energy, kinetic and potential energy, momentum, angular momentum and temperature
+    fn observables<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let observables = Observables::measure(self.simulation.as_ref());
+        let dict = PyDict::new(py);
+        dict.set_item("energy", observables.energy)?;
+        dict.set_item("kinetic", observables.energy_breakdown.kinetic())?;
+        dict.set_item("potential", observables.energy_breakdown.potential())?;
+        dict.set_item("momentum", vector(py, observables.momentum))?;
+        dict.set_item("angular_momentum", vector(py, observables.angular_momentum))?;
+        dict.set_item("temperature", observables.temperature)?;
+        Ok(dict)
+    }
+
+    /// Advance by `steps` steps of `dt`, sampling the state before the first step, every
+    /// `stride` steps and after the last step.
+    ///
+    /// Returns a dict of arrays with one entry per sample: time (k,), positions, orientations,
+    /// velocities and angular_velocities (k, n, 3), energy (k,), momentum and angular_momentum
+    /// (k, 3).
+    #[pyo3(signature = (dt, steps, stride=1))]
+    fn run<'py>(
+        &mut self,
+        py: Python<'py>,
+        dt: f64,
+        steps: u64,
+        stride: u64,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let config = RunConfig {
+            integrator: self.integrator,
+            dt,
+            length: Length::Steps(steps),
+            stride,
+            ..RunConfig::default()
+        };
+
+        let start = self.time;
+        let mut samples: Vec<(f64, Vec<ObjectState>, Observables)> = vec![];
+        batch::run(
+            self.simulation.as_mut(),
+            &config,
+            |time, simulation, diagnostics| {
+                let states = simulation
+                    .get_objects()
+                    .iter()
+                    .map(|object| {
+                        [
+                            object.get_pos(),
+                            object.get_orientation(),
+                            object.get_velocity(),
+                            object.get_angular_velocity(),
+                        ]
+                    })
+                    .collect();
+                samples.push((start + time, states, *diagnostics.current()));
+                Ok(())
+            },
+        )?;
+        self.time = samples.last().map_or(self.time, |(time, _, _)| *time);
+
+        let k = samples.len();
+        let n = samples.first().map_or(0, |(_, states, _)| states.len());
+        let dict = PyDict::new(py);
+        dict.set_item(
+            "time",
+            Array1::from_iter(samples.iter().map(|(time, _, _)| *time)).into_pyarray(py),
+        )?;
+        let names = [
+            "positions",
+            "orientations",
+            "velocities",
+            "angular_velocities",
+        ];
+        for (quantity, name) in names.iter().enumerate() {
+            let array = Array3::from_shape_fn((k, n, 3), |(sample, object, axis)| {
+                samples[sample].1[object][quantity][axis]
+            });
+            dict.set_item(*name, array.into_pyarray(py))?;
+        }
+        dict.set_item(
+            "energy",
+            Array1::from_iter(samples.iter().map(|(_, _, o)| o.energy)).into_pyarray(py),
+        )?;
+        dict.set_item(
+            "momentum",
+            vectors(py, samples.iter().map(|(_, _, o)| o.momentum)),
+        )?;
+        dict.set_item(
+            "angular_momentum",
+            vectors(py, samples.iter().map(|(_, _, o)| o.angular_momentum)),
+        )?;
+        Ok(dict)
+    }
+}
+
+impl Simulation {
+    fn scalars<'py>(
+        &self,
+        py: Python<'py>,
+        value: impl Fn(&dyn Object) -> f64,
+    ) -> Bound<'py, PyArray1<f64>> {
+        Array1::from_iter(self.simulation.get_objects().into_iter().map(value)).into_pyarray(py)
+    }
+
+    fn vectors<'py>(
+        &self,
+        py: Python<'py>,
+        value: impl Fn(&dyn Object) -> Vector3<f64>,
+    ) -> Bound<'py, PyArray2<f64>> {
+        vectors(py, self.simulation.get_objects().into_iter().map(value))
+    }
+}
+
+fn vector(py: Python, v: Vector3<f64>) -> Bound<PyArray1<f64>> {
+    Array1::from(vec![v.x, v.y, v.z]).into_pyarray(py)
+}
+
+fn vectors(py: Python, values: impl Iterator<Item = Vector3<f64>>) -> Bound<PyArray2<f64>> {
+    let values: Vec<_> = values.collect();
+    Array2::from_shape_fn((values.len(), 3), |(i, axis)| values[i][axis]).into_pyarray(py)
+}
+
+fn point<'a>(mut values: impl Iterator<Item = &'a f64>) -> Vector3<f64> {
+    let mut next = || *values.next().unwrap();
+    Vector3::new(next(), next(), next())
+}
+
+/// Dipoles and point charges interacting through Coulomb's law
+#[pymodule]
+fn dipole_simulation(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_class::<Simulation>()?;
+    Ok(())
+}
@@ -1,8 +1,8 @@
-pub(self) use self::styles::*;
-pub(self) use self::render_trait::*;
+use self::render_trait::*;
+use self::styles::*;
 use crate::app::Assets;
+use crate::app::CompareView;
 use crate::app::State;
-use crate::canvas::{CANVAS_HEIGHT, CANVAS_WIDTH};
 use crate::shader::ShaderSystem;
 use js_sys::Reflect;
 use std::cell::RefCell;
@@ -10,9 +10,9 @@ use std::collections::HashMap;
 use web_sys::WebGlRenderingContext as GL;
 use web_sys::*;
 
-mod styles;
 mod render_meshes;
 mod render_trait;
+mod styles;
 
 struct VaoExtension {
     oes_vao_ext: js_sys::Object,
@@ -23,8 +23,6 @@ struct Vao(js_sys::Object);
 
 pub struct WebRenderer {
     shader_sys: ShaderSystem,
-    #[allow(unused)]
-    depth_texture_ext: Option<js_sys::Object>,
     vao_ext: VaoExtension,
 }
 
@@ -32,8 +30,8 @@ impl WebRenderer {
     pub fn new(gl: &WebGlRenderingContext) -> WebRenderer {
         let shader_sys = ShaderSystem::new(gl);
 
-        let depth_texture_ext = gl
-            .get_extension("WEBGL_depth_texture")
+        // Enabling the extension is all that's needed, there's nothing to keep hold of
+        gl.get_extension("WEBGL_depth_texture")
             .expect("Depth texture extension");
 
         let oes_vao_ext = gl
@@ -47,7 +45,6 @@ impl WebRenderer {
         };
 
         WebRenderer {
-            depth_texture_ext,
             shader_sys,
             vao_ext,
         }
@@ -61,9 +58,35 @@ impl WebRenderer {
         // Position is positive instead of negative for.. mathematical reasons..
         let clip_plane = [0., 1., 0., above];
 
-        gl.viewport(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
-
-        self.render_meshes(gl, state, assets, clip_plane, false);
+        // The drawing buffer is in device pixels, which may be more than the canvas is shown at
+        let (canvas_width, canvas_height) = (gl.drawing_buffer_width(), gl.drawing_buffer_height());
+
+        let simulation = (state.simulation(), state.previous_poses());
+        let comparison = state
+            .comparison()
+            .map(|comparison| (comparison.simulation(), state.comparison_previous_poses()));
+        match (comparison, state.compare_view()) {
+            (Some(comparison), CompareView::Split) => {
+                // Halves side by side, as tall as they are wide, so that the camera's aspect ratio
+                // still holds
+                let (width, height) = (canvas_width / 2, canvas_height / 2);
+                let bottom = (canvas_height - height) / 2;
+
+                gl.viewport(0, bottom, width, height);
+                self.render_meshes(gl, state, assets, clip_plane, false, simulation, None);
+                self.render_gizmo(gl, state, assets);
+                self.render_arrows(gl, state, assets);
+
+                gl.viewport(width, bottom, width, height);
+                self.render_meshes(gl, state, assets, clip_plane, false, comparison, None);
+            }
+            (comparison, _) => {
+                gl.viewport(0, 0, canvas_width, canvas_height);
+                self.render_meshes(gl, state, assets, clip_plane, false, simulation, comparison);
+                self.render_gizmo(gl, state, assets);
+                self.render_arrows(gl, state, assets);
+            }
+        }
     }
 
     fn create_vao(&self) -> Vao {
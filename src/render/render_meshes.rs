@@ -3,72 +3,50 @@ use crate::render::FlatRenderOpts;
 use crate::render::Render;
 use crate::render::WebRenderer;
 use crate::shader::ShaderKind;
-use crate::simulation::dipole::Object;
 use crate::simulation::dipole::Objects;
-use crate::webgl_object::WebGLObject;
+use crate::simulation::dipole::Simulatable;
 use crate::Assets;
+use crate::Handle;
+use crate::Motion;
+use crate::Poses;
 use crate::State;
+use crate::AXES;
 use nalgebra::Vector3;
 use web_sys::WebGlRenderingContext as GL;
 
-fn render_field_lines(
-    web: &WebRenderer,
-    gl: &GL,
-    state: &State,
-    assets: &Assets,
-    flip_camera_y: bool,
-    start_pos: Vector3<f64>,
-) {
-    let shader = web.shader_sys.get_shader(&ShaderKind::Flat).unwrap();
-    web.shader_sys.use_program(gl, ShaderKind::Flat);
+/// How much a ghosted comparison is darkened
+const GHOST_SHADE: f32 = 0.35;
 
-    let line_opts = FlatRenderOpts {
-        pos: Vector3::new(0.0, 0.0, 0.0),
-        orient: Vector3::new(0.0, 0.0, 0.0),
-        color: Vector3::new(1., 0., 1.),
-        flip_camera_y,
-        as_lines: true,
-    };
+/// Colour of the arrows showing velocities
+const VELOCITY_COLOR: [f32; 3] = [0., 1., 1.];
 
-    let mut field_lines: Vec<Vector3<f32>> = Vec::new();
-
-    field_lines.push(start_pos.cast());
-    for i in 0..1 {
-        let field = state
-            .simulation
-            .get_field(field_lines[i].cast())
-            .normalize()
-            * 1.0;
-        field_lines.push(Vector3::new(
-            field_lines[i].x + field.x as f32,
-            field_lines[i].y + field.y as f32,
-            field_lines[i].z + field.z as f32,
-        ));
-    }
+/// Colour of the arrows showing angular velocities
+const ANGULAR_VELOCITY_COLOR: [f32; 3] = [1., 0., 1.];
 
-    let object = WebGLObject {
-        vertices: field_lines
-            .iter()
-            .flat_map(|v| v.as_slice())
-            .copied()
-            .collect(),
-        indices: vec![],
-        normals: vec![],
-    };
-    // web_sys::console::log_1(&format!("field lines: {:?}", object.vertices).into());
+/// Colour the selected object is brightened towards
+const HIGHLIGHT: [f32; 3] = [1., 1., 0.];
 
-    let line = Flat {
-        object: &object,
-        shader,
-        opts: &line_opts,
-    };
+/// How an object's colours are adjusted
+#[derive(Clone, Copy)]
+struct Tint {
+    /// Multiplies every colour, to fade ghosts
+    shade: f32,
+    /// Whether the object is selected
+    highlighted: bool,
+}
 
-    // line.buffer_attributes(gl);
-    web.prepare_for_render(gl, &line, "FieldLines");
-    line.buffer_attributes(gl);
-    line.render(gl, state);
+impl Tint {
+    fn apply(&self, color: Vector3<f32>) -> Vector3<f32> {
+        let color = if self.highlighted {
+            color.lerp(&HIGHLIGHT.into(), 0.6)
+        } else {
+            color
+        };
+        color * self.shade
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_dipole(
     web: &WebRenderer,
     gl: &GL,
@@ -78,6 +56,7 @@ fn render_dipole(
     pos: Vector3<f32>,
     orientation: Vector3<f32>,
     offset: f32,
+    tint: Tint,
 ) {
     // Render Spheres
     let shader = web.shader_sys.get_shader(&ShaderKind::Flat).unwrap();
@@ -87,8 +66,9 @@ fn render_dipole(
     let mesh_opts = FlatRenderOpts {
         pos: negative_pos,
         orient: Vector3::zeros(),
+        scale: 1.,
         flip_camera_y,
-        color: Vector3::new(0., 0.0, 1.),
+        color: tint.apply(Vector3::new(0., 0.0, 1.)),
         as_lines: false,
     };
     let mesh_name = "Sphere";
@@ -106,8 +86,9 @@ fn render_dipole(
     let mesh_opts = FlatRenderOpts {
         pos: positive_pos,
         orient: Vector3::zeros(),
+        scale: 1.,
         flip_camera_y,
-        color: Vector3::new(1., 0., 0.),
+        color: tint.apply(Vector3::new(1., 0., 0.)),
         as_lines: false,
     };
 
@@ -122,7 +103,8 @@ fn render_dipole(
     let line_opts = FlatRenderOpts {
         pos,
         orient: orientation,
-        color: Vector3::new(1., 1., 1.),
+        scale: 1.,
+        color: tint.apply(Vector3::new(1., 1., 1.)),
         flip_camera_y,
         as_lines: true,
     };
@@ -139,6 +121,7 @@ fn render_dipole(
     line.render(gl, state);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_charge(
     web: &WebRenderer,
     gl: &GL,
@@ -146,6 +129,7 @@ fn render_charge(
     assets: &Assets,
     flip_camera_y: bool,
     pos: Vector3<f32>,
+    tint: Tint,
 ) {
     let shader = web.shader_sys.get_shader(&ShaderKind::Flat).unwrap();
     web.shader_sys.use_program(gl, ShaderKind::Flat);
@@ -153,8 +137,9 @@ fn render_charge(
     let mesh_opts = FlatRenderOpts {
         pos,
         orient: Vector3::zeros(),
+        scale: 1.,
         flip_camera_y,
-        color: Vector3::new(0., 1., 0.),
+        color: tint.apply(Vector3::new(0., 1., 0.)),
         as_lines: false,
     };
     let mesh_name = "Sphere";
@@ -169,13 +154,14 @@ fn render_charge(
     sphere.render(gl, state);
 }
 
-fn render_axis(web: &WebRenderer, gl: &GL, state: &State, assets: &Assets, flip_camera_y: bool) {
+fn render_axis(web: &WebRenderer, gl: &GL, state: &State, _assets: &Assets, flip_camera_y: bool) {
     let shader = web.shader_sys.get_shader(&ShaderKind::Flat).unwrap();
     web.shader_sys.use_program(gl, ShaderKind::Flat);
 
     let line_opts = FlatRenderOpts {
         pos: Vector3::zeros(),
         orient: Vector3::zeros(),
+        scale: 1.,
         color: Vector3::new(1., 0., 0.),
         flip_camera_y,
         as_lines: true,
@@ -195,6 +181,7 @@ fn render_axis(web: &WebRenderer, gl: &GL, state: &State, assets: &Assets, flip_
     let line_opts = FlatRenderOpts {
         pos: Vector3::zeros(),
         orient: Vector3::zeros(),
+        scale: 1.,
         color: Vector3::new(0., 1., 0.),
         flip_camera_y,
         as_lines: true,
@@ -214,6 +201,7 @@ fn render_axis(web: &WebRenderer, gl: &GL, state: &State, assets: &Assets, flip_
     let line_opts = FlatRenderOpts {
         pos: Vector3::zeros(),
         orient: Vector3::zeros(),
+        scale: 1.,
         color: Vector3::new(0., 0., 1.),
         flip_camera_y,
         as_lines: true,
@@ -232,6 +220,9 @@ fn render_axis(web: &WebRenderer, gl: &GL, state: &State, assets: &Assets, flip_
 }
 
 impl WebRenderer {
+    /// Draw the scene with the objects of `simulation`, and those of `ghost` faded over them.
+    /// Each is drawn part way along its last step from the poses given with it.
+    #[allow(clippy::too_many_arguments)]
     pub(in crate::render) fn render_meshes(
         &self,
         gl: &GL,
@@ -239,22 +230,122 @@ impl WebRenderer {
         assets: &Assets,
         _clip_plane: [f32; 4],
         flip_camera_y: bool,
+        simulation: (&dyn Simulatable, &Poses),
+        ghost: Option<(&dyn Simulatable, &Poses)>,
     ) {
         if !state.show_scenery() {
             return;
         }
 
-        /* for i in (-5..5).step_by(3) {
-            for j in (-5..5).step_by(3) {
-                for k in (-5..5).step_by(3) {
-                    render_field_lines(self, gl, state, assets, flip_camera_y, Vector3::new(i as f64, j as f64, k as f64));
-                }
+        render_axis(self, gl, state, assets, flip_camera_y);
+
+        self.render_objects(gl, state, assets, flip_camera_y, simulation, 1.);
+        if let Some(ghost) = ghost {
+            self.render_objects(gl, state, assets, flip_camera_y, ghost, GHOST_SHADE);
+        }
+    }
+
+    /// Draw the handles around the selected object, over everything else so they can always be
+    /// grabbed
+    pub(in crate::render) fn render_gizmo(&self, gl: &GL, state: &State, assets: &Assets) {
+        let gizmo = match state.gizmo() {
+            Some(gizmo) => gizmo,
+            None => return,
+        };
+        let shader = self.shader_sys.get_shader(&ShaderKind::Flat).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::Flat);
+        gl.disable(GL::DEPTH_TEST);
+
+        let mut handles = vec![];
+        for (i, axis) in AXES.iter().enumerate() {
+            handles.push((Handle::Axis(i), "UnitLine", *axis, gizmo.size));
+            if gizmo.rings {
+                handles.push((Handle::Ring(i), "Ring", *axis, gizmo.ring_radius()));
             }
-        } */
+        }
+        for (handle, mesh_name, axis, scale) in handles {
+            let axis = Vector3::from(axis).cast();
+            let color = if state.dragging() == Some(handle) {
+                HIGHLIGHT.into()
+            } else {
+                axis
+            };
+            let opts = FlatRenderOpts {
+                pos: gizmo.center.cast(),
+                orient: axis,
+                scale: scale as f32,
+                color,
+                flip_camera_y: false,
+                as_lines: true,
+            };
+            let mesh = Flat {
+                object: assets.get_mesh(mesh_name).expect(mesh_name),
+                shader,
+                opts: &opts,
+            };
+            self.prepare_for_render(gl, &mesh, mesh_name);
+            mesh.render(gl, state);
+        }
 
-        render_axis(self, gl, state, assets, flip_camera_y);
+        gl.enable(GL::DEPTH_TEST);
+    }
 
-        for object in state.simulation.get_objects() {
+    /// Draw the arrows showing the motion the slingshot tool sets, over everything else
+    pub(in crate::render) fn render_arrows(&self, gl: &GL, state: &State, assets: &Assets) {
+        let shader = self.shader_sys.get_shader(&ShaderKind::Flat).unwrap();
+        self.shader_sys.use_program(gl, ShaderKind::Flat);
+        gl.disable(GL::DEPTH_TEST);
+
+        let mesh_name = "Arrow";
+        for arrow in state.arrows() {
+            let length = arrow.end() - arrow.start;
+            if length.norm() <= f64::EPSILON {
+                continue;
+            }
+            let color = match arrow.motion {
+                Motion::Linear => VELOCITY_COLOR,
+                Motion::Angular => ANGULAR_VELOCITY_COLOR,
+            };
+            let highlighted = state.dragging() == Some(Handle::Arrow(arrow.motion))
+                && state.selected() == Some(arrow.index);
+            let color = if highlighted { HIGHLIGHT } else { color };
+            let opts = FlatRenderOpts {
+                pos: arrow.start.cast(),
+                orient: length.normalize().cast(),
+                scale: length.norm() as f32,
+                color: color.into(),
+                flip_camera_y: false,
+                as_lines: true,
+            };
+            let mesh = Flat {
+                object: assets.get_mesh(mesh_name).expect(mesh_name),
+                shader,
+                opts: &opts,
+            };
+            self.prepare_for_render(gl, &mesh, mesh_name);
+            mesh.render(gl, state);
+        }
+
+        gl.enable(GL::DEPTH_TEST);
+    }
+
+    fn render_objects(
+        &self,
+        gl: &GL,
+        state: &State,
+        assets: &Assets,
+        flip_camera_y: bool,
+        (simulation, poses): (&dyn Simulatable, &Poses),
+        shade: f32,
+    ) {
+        let alpha = state.render_alpha();
+        for (index, object) in simulation.get_objects().into_iter().enumerate() {
+            let tint = Tint {
+                shade,
+                highlighted: state.selected() == Some(index),
+            };
+            let (position, orientation) =
+                poses.interpolate(index, object.get_pos(), object.get_orientation(), alpha);
             match object.get_type() {
                 Objects::Dipole => render_dipole(
                     self,
@@ -262,9 +353,10 @@ impl WebRenderer {
                     state,
                     assets,
                     flip_camera_y,
-                    object.get_pos().map(|x| x as f32),
-                    object.get_orientation().map(|x| x as f32),
+                    position.map(|x| x as f32),
+                    orientation.map(|x| x as f32),
                     object.get_offset() as f32,
+                    tint,
                 ),
                 Objects::Charge => render_charge(
                     self,
@@ -272,7 +364,8 @@ impl WebRenderer {
                     state,
                     assets,
                     flip_camera_y,
-                    object.get_pos().map(|x| x as f32),
+                    position.map(|x| x as f32),
+                    tint,
                 ),
             }
         }
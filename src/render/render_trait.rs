@@ -1,13 +1,10 @@
 use crate::shader::Shader;
-use crate::shader::ShaderKind;
 use crate::State;
 use js_sys::WebAssembly;
 use wasm_bindgen::JsCast;
 use web_sys::WebGlRenderingContext as GL;
 
 pub trait Render<'a> {
-    fn shader_kind() -> ShaderKind;
-
     fn shader(&'a self) -> &'a Shader;
 
     fn buffer_attributes(&self, gl: &GL);
@@ -32,24 +29,6 @@ pub trait Render<'a> {
         gl.vertex_attrib_pointer_with_i32(attrib, size, GL::FLOAT, false, 0, 0);
     }
 
-    fn buffer_u8_data(gl: &GL, data: &[u8], attrib: u32, size: i32) {
-        let memory_buffer = wasm_bindgen::memory()
-            .dyn_into::<WebAssembly::Memory>()
-            .unwrap()
-            .buffer();
-
-        let data_location = data.as_ptr() as u32;
-
-        let data_array = js_sys::Uint8Array::new(&memory_buffer)
-            .subarray(data_location, data_location + data.len() as u32);
-
-        let buffer = gl.create_buffer().unwrap();
-
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer));
-        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &data_array, GL::STATIC_DRAW);
-        gl.vertex_attrib_pointer_with_i32(attrib, size, GL::UNSIGNED_BYTE, false, 0, 0);
-    }
-
     fn buffer_u16_indices(gl: &GL, indices: &[u16]) {
         let memory_buffer = wasm_bindgen::memory()
             .dyn_into::<WebAssembly::Memory>()
@@ -1,11 +1,11 @@
 use crate::app::State;
 use crate::render::Render;
 use crate::shader::Shader;
-use crate::shader::ShaderKind;
 use crate::webgl_object::WebGLObject;
 use nalgebra::Matrix4;
 use nalgebra::Rotation3;
-use nalgebra::{Isometry3, Vector3};
+use nalgebra::Vector3;
+use std::f32::consts::PI;
 use web_sys::WebGlRenderingContext as GL;
 use web_sys::*;
 
@@ -19,15 +19,13 @@ pub struct FlatRenderOpts {
     pub color: Vector3<f32>,
     pub pos: Vector3<f32>,
     pub orient: Vector3<f32>,
+    /// Multiplies the size of the mesh
+    pub scale: f32,
     pub as_lines: bool,
     pub flip_camera_y: bool,
 }
 
 impl<'a> Render<'a> for Flat<'a> {
-    fn shader_kind() -> ShaderKind {
-        ShaderKind::Flat
-    }
-
     fn shader(&'a self) -> &'a Shader {
         self.shader
     }
@@ -56,23 +54,31 @@ impl<'a> Render<'a> for Flat<'a> {
         let perspective_uni = shader.get_uniform_location(gl, "perspective");
         let color_uni = shader.get_uniform_location(gl, "uColor");
 
-        let mut view = if opts.flip_camera_y {
+        let view = if opts.flip_camera_y {
             state.camera().view_flipped_y()
         } else {
             state.camera().view()
         };
-        gl.uniform_matrix4fv_with_f32_array(view_uni.as_ref(), false, &mut view);
+        gl.uniform_matrix4fv_with_f32_array(view_uni.as_ref(), false, &view);
 
         let mut model = Matrix4::new_translation(&Vector3::new(pos.x, pos.y, pos.z));
-        if let Some(rotation) = Rotation3::rotation_between(&Vector3::new(1., 0., 0.), &opts.orient) {
-            model *= rotation.to_homogeneous();
-        }
+        let rotation = if opts.orient.norm() < 1e-6 {
+            // Nothing to point along, such as the orientation of a point charge
+            Rotation3::identity()
+        } else {
+            Rotation3::rotation_between(&Vector3::new(1., 0., 0.), &opts.orient)
+                // Only pointing straight back along x has no rotation between, and a half turn
+                // does
+                .unwrap_or_else(|| Rotation3::from_axis_angle(&Vector3::y_axis(), PI))
+        };
+        model *= rotation.to_homogeneous();
+        model *= Matrix4::new_scaling(opts.scale);
         let mut model_array = [0.; 16];
         model_array.copy_from_slice(model.as_slice());
-        gl.uniform_matrix4fv_with_f32_array(model_uni.as_ref(), false, &mut model_array);
+        gl.uniform_matrix4fv_with_f32_array(model_uni.as_ref(), false, &model_array);
 
-        let mut perspective = state.camera().projection();
-        gl.uniform_matrix4fv_with_f32_array(perspective_uni.as_ref(), false, &mut perspective);
+        let perspective = state.camera().projection();
+        gl.uniform_matrix4fv_with_f32_array(perspective_uni.as_ref(), false, &perspective);
 
         gl.uniform3f(color_uni.as_ref(), opts.color.x, opts.color.y, opts.color.z);
 
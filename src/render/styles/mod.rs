@@ -1,5 +1,3 @@
 mod flat;
-mod points;
 
 pub use self::flat::*;
-pub use self::points::*;
@@ -57,7 +57,6 @@ impl ShaderSystem {
 pub enum ShaderKind {
     Mesh,
     Flat,
-    Point,
 }
 
 /// One per ShaderKind
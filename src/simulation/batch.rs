@@ -0,0 +1,205 @@
+//! Running a simulation unattended for a fixed number of steps
+
+use crate::simulation::diagnostics::ConservationDiagnostics;
+use crate::simulation::diagnostics::DriftReport;
+use crate::simulation::diagnostics::Observables;
+use crate::simulation::dipole::Simulatable;
+use crate::simulation::integrator::Integrator;
+use std::io::{self, Write};
+
+/// How long a run lasts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    /// A fixed number of steps
+    Steps(u64),
+    /// Enough steps to cover this much simulated time
+    Time(f64),
+}
+
+/// How to step a simulation through a run
+#[derive(Clone, Debug)]
+pub struct RunConfig {
+    /// The numerical method to step with
+    pub integrator: Integrator,
+    /// Simulated time per step
+    pub dt: f64,
+    /// When to stop
+    pub length: Length,
+    /// Steps between samples passed to the caller
+    pub stride: u64,
+    /// Relative drift above which the run is flagged
+    pub drift_threshold: f64,
+    /// Stop once two objects come closer than this. The model has no repulsion, so objects that
+    /// get this close are about to collapse onto each other and the run is no longer meaningful.
+    pub collapse_distance: Option<f64>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            integrator: Integrator::default(),
+            dt: 0.001,
+            length: Length::Steps(1000),
+            stride: 1,
+            drift_threshold: 0.01,
+            collapse_distance: None,
+        }
+    }
+}
+
+impl RunConfig {
+    /// Total number of steps in the run
+    pub fn steps(&self) -> u64 {
+        match self.length {
+            Length::Steps(steps) => steps,
+            // Allow for rounding, so that a time a whole number of steps long isn't one step over
+            Length::Time(time) => (time / self.dt - 1e-9).ceil().max(0.) as u64,
+        }
+    }
+}
+
+/// What happened over a run
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+    /// Steps taken
+    pub steps: u64,
+    /// Simulated time at the end of the run
+    pub time: f64,
+    /// Observables before the first step
+    pub initial: Observables,
+    /// Observables after the last step
+    pub last: Observables,
+    /// Drift at the end of the run
+    pub final_drift: DriftReport,
+    /// Largest drift of each quantity over the run
+    pub max_drift: DriftReport,
+    /// Simulated time at which the drift first exceeded the threshold
+    pub exceeded_at: Option<f64>,
+    /// Smallest distance between the centres of two objects at the end of the run
+    pub final_separation: f64,
+    /// Simulated time at which the objects collapsed, if the run was stopped early because of it
+    pub collapsed_at: Option<f64>,
+}
+
+/// Smallest distance between the centres of any two objects. Infinite with fewer than two objects.
+pub fn min_separation(simulation: &dyn Simulatable) -> f64 {
+    closest_pair(simulation).map_or(f64::INFINITY, |(_, _, distance)| distance)
+}
+
+/// The indices of the two objects whose centres are closest together and their distance, or
+/// `None` with fewer than two objects
+pub fn closest_pair(simulation: &dyn Simulatable) -> Option<(usize, usize, f64)> {
+    let positions: Vec<_> = simulation
+        .get_objects()
+        .iter()
+        .map(|object| object.get_pos())
+        .collect();
+    let mut closest = None;
+    for (i, a) in positions.iter().enumerate() {
+        for (j, b) in positions.iter().enumerate().skip(i + 1) {
+            let distance = (a - b).norm();
+            if closest.is_none_or(|(_, _, min)| distance < min) {
+                closest = Some((i, j, distance));
+            }
+        }
+    }
+    closest
+}
+
+/// Step `simulation` through a run, calling `sample` before the first step, after every `stride`
+/// steps and after the last step.
+///
+/// The run ends early if the objects collapse or the energy stops being finite.
+///
+/// Sampling stops the run early if it returns an error.
+pub fn run<F>(
+    simulation: &mut dyn Simulatable,
+    config: &RunConfig,
+    mut sample: F,
+) -> io::Result<RunSummary>
+where
+    F: FnMut(f64, &dyn Simulatable, &ConservationDiagnostics) -> io::Result<()>,
+{
+    simulation.set_integrator(config.integrator);
+    let mut diagnostics = ConservationDiagnostics::new(simulation, config.drift_threshold);
+    let initial = *diagnostics.current();
+    let stride = config.stride.max(1);
+    let steps = config.steps();
+
+    sample(0., simulation, &diagnostics)?;
+
+    let mut time = 0.;
+    let mut taken = 0;
+    let mut collapsed_at = None;
+    for step in 1..=steps {
+        simulation.update(config.dt);
+        time = step as f64 * config.dt;
+        taken = step;
+        diagnostics.update(simulation, time);
+
+        let collapsed = !diagnostics.current().energy.is_finite()
+            || config
+                .collapse_distance
+                .is_some_and(|distance| min_separation(simulation) < distance);
+        if collapsed {
+            collapsed_at = Some(time);
+        }
+
+        if step % stride == 0 || step == steps || collapsed {
+            sample(time, simulation, &diagnostics)?;
+        }
+        if collapsed {
+            break;
+        }
+    }
+
+    Ok(RunSummary {
+        steps: taken,
+        time,
+        initial,
+        last: *diagnostics.current(),
+        final_drift: *diagnostics.latest(),
+        max_drift: *diagnostics.max(),
+        exceeded_at: diagnostics.exceeded_at(),
+        final_separation: min_separation(simulation),
+        collapsed_at,
+    })
+}
+
+/// Write the column names matching `write_observables_row`
+pub fn write_observables_header<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(
+        out,
+        "time,energy,kinetic,potential,px,py,pz,lx,ly,lz,temperature,\
+         energy_drift,momentum_drift,angular_momentum_drift"
+    )
+}
+
+/// Write one CSV row of observables and their relative drift
+pub fn write_observables_row<W: Write>(
+    out: &mut W,
+    time: f64,
+    observables: &Observables,
+    drift: &DriftReport,
+) -> io::Result<()> {
+    let p = observables.momentum;
+    let l = observables.angular_momentum;
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        time,
+        observables.energy,
+        observables.energy_breakdown.kinetic(),
+        observables.energy_breakdown.potential(),
+        p.x,
+        p.y,
+        p.z,
+        l.x,
+        l.y,
+        l.z,
+        observables.temperature,
+        drift.energy.relative,
+        drift.momentum.relative,
+        drift.angular_momentum.relative
+    )
+}
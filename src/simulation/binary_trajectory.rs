@@ -0,0 +1,358 @@
+//! A compact binary container for trajectories.
+//!
+//! Layout (all numbers little endian):
+//!
+//! ```text
+//! header:  magic "DPLTRAJ\0", version u32,
+//!          units (u32 length + utf8), integrator (u32 length + utf8),
+//!          object count u32, per object: kind u8, mass f64, charge f64,
+//!          frame count u64, index offset u64
+//! frames:  time f64, per object: position, orientation, velocity, angular velocity (3 x f64 each),
+//!          offset f64
+//! index:   per frame: time f64, byte offset u64
+//! ```
+//!
+//! Every frame has the same size, so frame `i` can be read without scanning the ones before it.
+//! The index at the end of the file additionally lets readers seek by simulated time.
+
+use crate::simulation::dipole::Objects;
+use crate::simulation::trajectory::Frame;
+use crate::simulation::trajectory::ObjectState;
+use nalgebra::Vector3;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+const MAGIC: &[u8; 8] = b"DPLTRAJ\0";
+const VERSION: u32 = 1;
+
+/// Size in bytes of the per object part of a frame
+const OBJECT_SIZE: u64 = 13 * 8;
+
+/// Constant properties of one object in the trajectory
+#[derive(Clone, Debug)]
+pub struct ObjectMetadata {
+    /// What kind of object this is
+    pub kind: Objects,
+    /// Total mass
+    pub mass: f64,
+    /// The charge of a point charge, or the magnitude of each charge of a dipole
+    pub charge: f64,
+}
+
+/// Describes the run a trajectory was recorded from
+#[derive(Clone, Debug)]
+pub struct Header {
+    /// Description of the unit system the values are expressed in
+    pub units: String,
+    /// Name of the integrator that produced the run
+    pub integrator: String,
+    /// The objects present in every frame, in order
+    pub objects: Vec<ObjectMetadata>,
+}
+
+impl Header {
+    /// Build a header describing the objects of a frame
+    pub fn from_frame(frame: &Frame, units: &str, integrator: &str) -> Header {
+        Header {
+            units: units.to_string(),
+            integrator: integrator.to_string(),
+            objects: frame
+                .objects
+                .iter()
+                .map(|object| ObjectMetadata {
+                    kind: object.kind,
+                    mass: object.mass,
+                    charge: object.charge,
+                })
+                .collect(),
+        }
+    }
+
+    fn frame_size(&self) -> u64 {
+        8 + self.objects.len() as u64 * OBJECT_SIZE
+    }
+}
+
+/// Streams frames into the binary trajectory format.
+///
+/// `finish` must be called once all frames have been written so that the frame count and index
+/// are filled in.
+pub struct TrajectoryWriter<W: Write + Seek> {
+    out: W,
+    header: Header,
+    counts_position: u64,
+    index: Vec<(f64, u64)>,
+}
+
+impl<W: Write + Seek> TrajectoryWriter<W> {
+    /// Write the header and get ready to receive frames
+    pub fn new(mut out: W, header: Header) -> io::Result<TrajectoryWriter<W>> {
+        out.write_all(MAGIC)?;
+        write_u32(&mut out, VERSION)?;
+        write_str(&mut out, &header.units)?;
+        write_str(&mut out, &header.integrator)?;
+        write_u32(&mut out, header.objects.len() as u32)?;
+        for object in header.objects.iter() {
+            let kind = match object.kind {
+                Objects::Charge => 0,
+                Objects::Dipole => 1,
+            };
+            out.write_all(&[kind])?;
+            write_f64(&mut out, object.mass)?;
+            write_f64(&mut out, object.charge)?;
+        }
+
+        // Placeholders for the frame count and index offset, patched in `finish`
+        let counts_position = out.stream_position()?;
+        write_u64(&mut out, 0)?;
+        write_u64(&mut out, 0)?;
+
+        Ok(TrajectoryWriter {
+            out,
+            header,
+            counts_position,
+            index: Vec::new(),
+        })
+    }
+
+    /// Append a frame. It must contain the objects described by the header.
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        if frame.objects.len() != self.header.objects.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame object count does not match the header",
+            ));
+        }
+
+        let position = self.out.stream_position()?;
+        self.index.push((frame.time, position));
+
+        write_f64(&mut self.out, frame.time)?;
+        for object in frame.objects.iter() {
+            for v in [
+                object.position,
+                object.orientation,
+                object.velocity,
+                object.angular_velocity,
+            ]
+            .iter()
+            {
+                write_vector(&mut self.out, v)?;
+            }
+            write_f64(&mut self.out, object.offset)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the frame index and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let index_position = self.out.stream_position()?;
+        for (time, position) in self.index.iter() {
+            write_f64(&mut self.out, *time)?;
+            write_u64(&mut self.out, *position)?;
+        }
+
+        self.out.seek(SeekFrom::Start(self.counts_position))?;
+        write_u64(&mut self.out, self.index.len() as u64)?;
+        write_u64(&mut self.out, index_position)?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// Random access reader for the binary trajectory format
+pub struct TrajectoryReader<R: Read + Seek> {
+    input: R,
+    header: Header,
+    index: Vec<(f64, u64)>,
+}
+
+impl<R: Read + Seek> TrajectoryReader<R> {
+    /// Read the header and frame index of a finished trajectory
+    pub fn open(mut input: R) -> io::Result<TrajectoryReader<R>> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a dipole trajectory file"));
+        }
+        let version = read_u32(&mut input)?;
+        if version != VERSION {
+            return Err(invalid_data("unsupported trajectory version"));
+        }
+
+        let units = read_str(&mut input)?;
+        let integrator = read_str(&mut input)?;
+        // Counts and lengths come from the file, so nothing is allocated up front on their word:
+        // a corrupt one runs out of data and fails instead
+        let object_count = read_u32(&mut input)?;
+        let mut objects = vec![];
+        for _ in 0..object_count {
+            let mut kind = [0];
+            input.read_exact(&mut kind)?;
+            let kind = match kind[0] {
+                0 => Objects::Charge,
+                1 => Objects::Dipole,
+                _ => return Err(invalid_data("unknown object kind")),
+            };
+            let mass = read_f64(&mut input)?;
+            let charge = read_f64(&mut input)?;
+            objects.push(ObjectMetadata { kind, mass, charge });
+        }
+
+        let frame_count = read_u64(&mut input)?;
+        let index_position = read_u64(&mut input)?;
+        if index_position == 0 {
+            return Err(invalid_data("trajectory was not finished"));
+        }
+
+        input.seek(SeekFrom::Start(index_position))?;
+        let mut index = vec![];
+        for _ in 0..frame_count {
+            let time = read_f64(&mut input)?;
+            let position = read_u64(&mut input)?;
+            index.push((time, position));
+        }
+
+        let header = Header {
+            units,
+            integrator,
+            objects,
+        };
+
+        Ok(TrajectoryReader {
+            input,
+            header,
+            index,
+        })
+    }
+
+    /// Metadata describing the run
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Number of frames
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether the trajectory has no frames
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The simulated time of every frame, in order
+    pub fn times(&self) -> impl Iterator<Item = f64> + '_ {
+        self.index.iter().map(|(time, _)| *time)
+    }
+
+    /// Index of the last frame recorded at or before `time`
+    pub fn frame_at_time(&self, time: f64) -> usize {
+        self.index
+            .partition_point(|(t, _)| *t <= time)
+            .saturating_sub(1)
+    }
+
+    /// Read frame number `frame`
+    pub fn read_frame(&mut self, frame: usize) -> io::Result<Frame> {
+        let (_, position) = *self
+            .index
+            .get(frame)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame out of range"))?;
+
+        let mut bytes = vec![0; self.header.frame_size() as usize];
+        self.input.seek(SeekFrom::Start(position))?;
+        self.input.read_exact(&mut bytes)?;
+        let mut bytes = &bytes[..];
+
+        let time = read_f64(&mut bytes)?;
+        let mut objects = Vec::with_capacity(self.header.objects.len());
+        for metadata in self.header.objects.iter() {
+            let position = read_vector(&mut bytes)?;
+            let orientation = read_vector(&mut bytes)?;
+            let velocity = read_vector(&mut bytes)?;
+            let angular_velocity = read_vector(&mut bytes)?;
+            let offset = read_f64(&mut bytes)?;
+            objects.push(ObjectState {
+                kind: metadata.kind,
+                mass: metadata.mass,
+                charge: metadata.charge,
+                offset,
+                position,
+                orientation,
+                velocity,
+                angular_velocity,
+            });
+        }
+
+        Ok(Frame { time, objects })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u32<W: Write>(out: &mut W, value: u32) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_u64<W: Write>(out: &mut W, value: u64) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_f64<W: Write>(out: &mut W, value: f64) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_str<W: Write>(out: &mut W, value: &str) -> io::Result<()> {
+    write_u32(out, value.len() as u32)?;
+    out.write_all(value.as_bytes())
+}
+
+fn write_vector<W: Write>(out: &mut W, value: &Vector3<f64>) -> io::Result<()> {
+    write_f64(out, value.x)?;
+    write_f64(out, value.y)?;
+    write_f64(out, value.z)
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(input: &mut R) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn read_str<R: Read>(input: &mut R) -> io::Result<String> {
+    let len = read_u32(input)?;
+    let mut bytes = vec![];
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "string runs past the end of the file",
+        ));
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("string is not utf8"))
+}
+
+fn read_vector<R: Read>(input: &mut R) -> io::Result<Vector3<f64>> {
+    Ok(Vector3::new(
+        read_f64(input)?,
+        read_f64(input)?,
+        read_f64(input)?,
+    ))
+}
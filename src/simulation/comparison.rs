@@ -0,0 +1,194 @@
+//! Running a scenario a second time with different settings, to see what the settings change
+
+use crate::simulation::dipole::{Edit, Model, Objects, Simulatable};
+use crate::simulation::integrator::Integrator;
+use crate::simulation::scenario::{ObjectSpec, Scenario};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// How a simulation is stepped, and how its dipoles act on each other
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The numerical method used for each step
+    #[serde(with = "integrator_name")]
+    pub integrator: Integrator,
+    /// Steps taken per update, so the time step is the update's `dt` divided by this
+    pub substeps: u32,
+    /// Distance from the centre to each charge of every dipole, or `None` to keep the offset of
+    /// the scenario
+    pub offset: Option<f64>,
+    /// How dipoles act on each other
+    pub model: Model,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            integrator: Integrator::default(),
+            substeps: 1,
+            offset: None,
+            model: Model::default(),
+        }
+    }
+}
+
+/// Integrators are written by name, as on the command line
+mod integrator_name {
+    use crate::simulation::integrator::Integrator;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(integrator: &Integrator, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(integrator.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Integrator, D::Error> {
+        String::deserialize(d)?.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Settings {
+    /// Parse settings from JSON. Missing fields keep their defaults.
+    pub fn from_json(json: &str) -> Result<Settings, String> {
+        serde_json::from_str(json).map_err(|err| format!("invalid settings: {}", err))
+    }
+}
+
+/// A simulation that is stepped according to its own settings
+pub struct Run {
+    simulation: Box<dyn Simulatable>,
+    settings: Settings,
+}
+
+impl Run {
+    /// Start a simulation of `scenario` that follows `settings`
+    pub fn new(scenario: &Scenario, settings: Settings) -> Result<Run, String> {
+        let mut run = Run {
+            simulation: scenario.build()?,
+            settings,
+        };
+        run.simulation.set_integrator(settings.integrator);
+        run.simulation.set_model(settings.model);
+        if let Some(offset) = settings.offset {
+            run.simulation.set_offset(offset);
+        }
+        Ok(run)
+    }
+
+    /// The simulation being run
+    pub fn simulation(&self) -> &dyn Simulatable {
+        self.simulation.as_ref()
+    }
+
+    /// The settings the simulation is run with
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Advance by `dt` seconds of simulated time, in `substeps` steps
+    pub fn update(&mut self, dt: f64) {
+        let substeps = self.settings.substeps.max(1);
+        for _ in 0..substeps {
+            self.simulation.update(dt / substeps as f64);
+        }
+    }
+
+    /// Return the simulation to its initial state
+    pub fn reset(&mut self) {
+        self.simulation.reset();
+    }
+
+    /// Set the uniform field applied on top of the field of the objects
+    pub fn set_external_field(&mut self, field: Vector3<f64>) {
+        self.simulation.set_external_field(field);
+    }
+
+    /// Change one value of an object. Offsets are left alone if the settings fix them.
+    pub fn edit_object(&mut self, index: usize, edit: Edit) -> Result<(), String> {
+        if let (Edit::Offset(_), Some(_)) = (edit, self.settings.offset) {
+            return Ok(());
+        }
+        self.simulation.edit_object(index, edit)
+    }
+
+    /// Change one value of the state an object resets to. Offsets are left alone if the settings
+    /// fix them.
+    pub fn edit_initial(&mut self, index: usize, edit: Edit) -> Result<(), String> {
+        if let (Edit::Offset(_), Some(_)) = (edit, self.settings.offset) {
+            return Ok(());
+        }
+        self.simulation.edit_initial(index, edit)
+    }
+
+    /// Add an object, giving it the offset of the settings if they fix one, and return its index
+    pub fn add_object(&mut self, object: &ObjectSpec) -> Result<usize, String> {
+        let index = self.simulation.add_object(object)?;
+        if let (Some(offset), Objects::Dipole) = (self.settings.offset, object.kind) {
+            self.simulation.edit_object(index, Edit::Offset(offset))?;
+        }
+        Ok(index)
+    }
+
+    /// Remove the `index`th object
+    pub fn remove_object(&mut self, index: usize) -> Result<(), String> {
+        self.simulation.remove_object(index)
+    }
+
+    /// Follow a change of offset, unless the settings fix the offset
+    pub fn set_offset(&mut self, offset: f64) {
+        if self.settings.offset.is_none() {
+            self.simulation.set_offset(offset);
+        }
+    }
+}
+
+/// How far apart two simulations of the same objects have drifted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Divergence {
+    /// Root mean square distance between the positions of corresponding objects
+    pub position: f64,
+    /// Largest distance between the positions of corresponding objects
+    pub max_position: f64,
+    /// Root mean square angle between the orientations of corresponding dipoles, in radians
+    pub orientation: f64,
+    /// Difference in total energy
+    pub energy: f64,
+}
+
+impl Divergence {
+    /// Compare the objects of two simulations pairwise. `None` if they hold different numbers of
+    /// objects.
+    pub fn measure(a: &dyn Simulatable, b: &dyn Simulatable) -> Option<Divergence> {
+        let (a_objects, b_objects) = (a.get_objects(), b.get_objects());
+        if a_objects.len() != b_objects.len() {
+            return None;
+        }
+
+        let mut divergence = Divergence {
+            energy: b.get_total_energy() - a.get_total_energy(),
+            ..Divergence::default()
+        };
+        if a_objects.is_empty() {
+            return Some(divergence);
+        }
+
+        let mut position = 0.;
+        let mut orientation = 0.;
+        for (a, b) in a_objects.iter().zip(b_objects.iter()) {
+            let distance = (a.get_pos() - b.get_pos()).norm();
+            position += distance * distance;
+            divergence.max_position = divergence.max_position.max(distance);
+
+            // Point charges have no orientation, which counts as no difference
+            let angle = a.get_orientation().angle(&b.get_orientation());
+            if angle.is_finite() {
+                orientation += angle * angle;
+            }
+        }
+        let n = a_objects.len() as f64;
+        divergence.position = (position / n).sqrt();
+        divergence.orientation = (orientation / n).sqrt();
+
+        Some(divergence)
+    }
+}
@@ -0,0 +1,233 @@
+//! Measuring how well a simulation conserves energy and momentum
+
+use crate::simulation::dipole::EnergyBreakdown;
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use nalgebra::Vector3;
+use std::collections::VecDeque;
+
+/// How many drift reports are kept before the oldest are discarded
+const HISTORY_LENGTH: usize = 4096;
+
+/// The conserved quantities of a simulation at one instant
+#[derive(Clone, Copy, Debug)]
+pub struct Observables {
+    /// Total energy
+    pub energy: f64,
+    /// Total energy split into its kinetic and potential parts
+    pub energy_breakdown: EnergyBreakdown,
+    /// Total linear momentum
+    pub momentum: Vector3<f64>,
+    /// Total angular momentum about the origin
+    pub angular_momentum: Vector3<f64>,
+    /// Kinetic energy per degree of freedom, with the Boltzmann constant taken as 1
+    pub temperature: f64,
+}
+
+impl Observables {
+    /// Measure the current state of a simulation
+    pub fn measure(simulation: &dyn Simulatable) -> Observables {
+        let energy_breakdown = simulation.get_energy_breakdown();
+
+        // A dipole is a linear rotor, so spinning about its own axis is not a degree of freedom
+        let degrees_of_freedom: usize = simulation
+            .get_objects()
+            .iter()
+            .map(|object| match object.get_type() {
+                Objects::Charge => 3,
+                Objects::Dipole => 5,
+            })
+            .sum();
+        let temperature = if degrees_of_freedom > 0 {
+            2. * energy_breakdown.kinetic() / degrees_of_freedom as f64
+        } else {
+            0.
+        };
+
+        Observables {
+            energy: energy_breakdown.total(),
+            energy_breakdown,
+            momentum: simulation.get_total_momentum(),
+            angular_momentum: simulation.get_total_angular_momentum(),
+            temperature,
+        }
+    }
+}
+
+/// How far a quantity has moved away from its reference value.
+///
+/// The relative drift falls back to the absolute drift when the reference is zero, as is the case
+/// for the momentum of a system that starts at rest.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Drift {
+    /// Magnitude of the difference from the reference
+    pub absolute: f64,
+    /// Absolute drift divided by the magnitude of the reference
+    pub relative: f64,
+}
+
+impl Drift {
+    fn new(absolute: f64, reference: f64) -> Drift {
+        let relative = if reference > f64::EPSILON {
+            absolute / reference
+        } else {
+            absolute
+        };
+
+        Drift { absolute, relative }
+    }
+
+    fn max(self, other: Drift) -> Drift {
+        Drift {
+            absolute: self.absolute.max(other.absolute),
+            relative: self.relative.max(other.relative),
+        }
+    }
+}
+
+/// The drift of every conserved quantity at one point in simulated time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DriftReport {
+    /// Simulated time of the measurement
+    pub time: f64,
+    /// Drift of the total energy
+    pub energy: Drift,
+    /// Drift of the total linear momentum
+    pub momentum: Drift,
+    /// Drift of the total angular momentum
+    pub angular_momentum: Drift,
+}
+
+impl DriftReport {
+    /// The largest relative drift of any quantity
+    pub fn worst(&self) -> f64 {
+        self.energy
+            .relative
+            .max(self.momentum.relative)
+            .max(self.angular_momentum.relative)
+    }
+}
+
+/// Tracks how well energy, linear momentum and angular momentum are conserved over a run.
+///
+/// The values measured when the diagnostics are created (or reset) are the reference that every
+/// later measurement is compared against.
+pub struct ConservationDiagnostics {
+    reference: Observables,
+    current: Observables,
+    latest: DriftReport,
+    max: DriftReport,
+    threshold: f64,
+    exceeded_at: Option<f64>,
+    history: VecDeque<DriftReport>,
+}
+
+impl ConservationDiagnostics {
+    /// `threshold` is the relative drift above which the run is flagged
+    pub fn new(simulation: &dyn Simulatable, threshold: f64) -> ConservationDiagnostics {
+        let reference = Observables::measure(simulation);
+
+        ConservationDiagnostics {
+            reference,
+            current: reference,
+            latest: DriftReport::default(),
+            max: DriftReport::default(),
+            threshold,
+            exceeded_at: None,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Take the current state of the simulation as the new reference and forget all drift
+    pub fn reset(&mut self, simulation: &dyn Simulatable) {
+        *self = ConservationDiagnostics::new(simulation, self.threshold);
+    }
+
+    /// Measure the simulation at simulated `time` and compare it against the reference
+    pub fn update(&mut self, simulation: &dyn Simulatable, time: f64) -> &DriftReport {
+        let current = Observables::measure(simulation);
+        let reference = &self.reference;
+
+        let report = DriftReport {
+            time,
+            energy: Drift::new(
+                (current.energy - reference.energy).abs(),
+                reference.energy.abs(),
+            ),
+            momentum: Drift::new(
+                (current.momentum - reference.momentum).norm(),
+                reference.momentum.norm(),
+            ),
+            angular_momentum: Drift::new(
+                (current.angular_momentum - reference.angular_momentum).norm(),
+                reference.angular_momentum.norm(),
+            ),
+        };
+
+        self.max = DriftReport {
+            time,
+            energy: self.max.energy.max(report.energy),
+            momentum: self.max.momentum.max(report.momentum),
+            angular_momentum: self.max.angular_momentum.max(report.angular_momentum),
+        };
+        if self.exceeded_at.is_none() && report.worst() > self.threshold {
+            self.exceeded_at = Some(time);
+        }
+
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(report);
+
+        self.current = current;
+        self.latest = report;
+        &self.latest
+    }
+
+    /// The values drift is measured against
+    pub fn reference(&self) -> &Observables {
+        &self.reference
+    }
+
+    /// The observables from the most recent update
+    pub fn current(&self) -> &Observables {
+        &self.current
+    }
+
+    /// The drift from the most recent update
+    pub fn latest(&self) -> &DriftReport {
+        &self.latest
+    }
+
+    /// The largest drift of each quantity seen since the reference was taken
+    pub fn max(&self) -> &DriftReport {
+        &self.max
+    }
+
+    /// Recent drift reports, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &DriftReport> {
+        self.history.iter()
+    }
+
+    /// Relative drift above which the run is flagged
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Change the relative drift above which the run is flagged. The flag is lowered, to be
+    /// raised by the next report over the new threshold.
+    pub fn set_threshold(&mut self, threshold: f64) {
+        self.threshold = threshold;
+        self.exceeded_at = None;
+    }
+
+    /// Whether any quantity has drifted further than the threshold
+    pub fn exceeded(&self) -> bool {
+        self.exceeded_at.is_some()
+    }
+
+    /// The simulated time at which the threshold was first exceeded
+    pub fn exceeded_at(&self) -> Option<f64> {
+        self.exceeded_at
+    }
+}
@@ -1,11 +1,47 @@
+//! Dipoles and point charges interacting through Coulomb's law
+
+use crate::simulation::integrator::Integrator;
+use crate::simulation::scenario::ObjectSpec;
 use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
 
+/// The kinds of object a simulation can contain
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Objects {
+    /// A single point charge
     Charge,
+    /// A rigid pair of opposite charges
     Dipole,
 }
 
+/// How dipoles act on each other
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Model {
+    /// Every charge of one dipole pulls on every charge of the other through Coulomb's law
+    #[default]
+    Finite,
+    /// Each dipole acts as an ideal point dipole of the same moment at its centre
+    Point,
+}
+
+impl Model {
+    /// Every model, in the order they are offered
+    pub const ALL: [Model; 2] = [Model::Finite, Model::Point];
+
+    /// The name used for the model in settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::Finite => "finite",
+            Model::Point => "point",
+        }
+    }
+}
+
+/// A body taking part in a simulation
 pub trait Object {
+    /// Apply the change in state computed by an integration step
     fn update(
         &mut self,
         d_pos: Vector3<f64>,
@@ -13,10 +49,22 @@ pub trait Object {
         d_orient: Vector3<f64>,
         d_ang_vel: Vector3<f64>,
     );
+    /// What kind of object this is
     fn get_type(&self) -> Objects;
+    /// Position of the centre of mass
     fn get_pos(&self) -> Vector3<f64>;
+    /// Unit vector from the negative to the positive charge. Zero for point charges.
     fn get_orientation(&self) -> Vector3<f64>;
+    /// Distance from the centre to each charge of a dipole. Zero for point charges.
     fn get_offset(&self) -> f64;
+    /// Velocity of the centre of mass
+    fn get_velocity(&self) -> Vector3<f64>;
+    /// Angular velocity about the centre of mass. Zero for point charges.
+    fn get_angular_velocity(&self) -> Vector3<f64>;
+    /// Total mass
+    fn get_mass(&self) -> f64;
+    /// The charge of a point charge, or the magnitude of each charge of a dipole
+    fn get_charge(&self) -> f64;
 }
 
 pub(crate) struct Dipole {
@@ -24,9 +72,11 @@ pub(crate) struct Dipole {
     position: Vector3<f64>,
     _position: Vector3<f64>,
     velocity: Vector3<f64>,
+    _velocity: Vector3<f64>,
     orientation: Vector3<f64>,
     _orientation: Vector3<f64>,
     angular_velocity: Vector3<f64>,
+    _angular_velocity: Vector3<f64>,
     charge: f64,
     offset: f64,
     moment: f64,
@@ -48,64 +98,77 @@ impl Dipole {
             position,
             _position: position,
             velocity,
+            _velocity: velocity,
             orientation,
             _orientation: orientation,
             angular_velocity,
+            _angular_velocity: angular_velocity,
             charge,
             offset,
             moment: mass * offset * offset,
-            charges: [
-                Charge {
-                    mass: mass / 2.,
-                    position: position + orientation * offset / 2.,
-                    velocity,
-                    charge: charge / 2.,
-                },
-                Charge {
-                    mass: mass / 2.,
-                    position: position - orientation * offset / 2.,
-                    velocity,
-                    charge: charge / 2.,
-                },
-            ],
+            charges: Dipole::constituents(mass, position, velocity, orientation, charge, offset),
         }
     }
+    fn constituents(
+        mass: f64,
+        position: Vector3<f64>,
+        velocity: Vector3<f64>,
+        orientation: Vector3<f64>,
+        charge: f64,
+        offset: f64,
+    ) -> [Charge; 2] {
+        [
+            Charge {
+                mass: mass / 2.,
+                position: position + orientation * offset / 2.,
+                velocity,
+                charge: charge / 2.,
+            },
+            Charge {
+                mass: mass / 2.,
+                position: position - orientation * offset / 2.,
+                velocity,
+                charge: charge / 2.,
+            },
+        ]
+    }
     fn reset(&mut self) {
         self.position = self._position;
         self.orientation = self._orientation;
-        self.velocity = Vector3::zeros();
-        self.angular_velocity = Vector3::zeros();
+        self.velocity = self._velocity;
+        self.angular_velocity = self._angular_velocity;
     }
+    /// Force and torque on this dipole, the `index`th of `sim`, when every dipole has the position
+    /// and orientation given in `states`
     fn force_torque(
         &self,
-        r: Vector3<f64>,
-        o: Vector3<f64>,
-        sim: &DipoleSimulation,
         index: usize,
+        states: &[(Vector3<f64>, Vector3<f64>)],
+        sim: &DipoleSimulation,
     ) -> (Vector3<f64>, Vector3<f64>) {
+        let (r, o) = states[index];
         let mut force = Vector3::new(0., 0., 0.);
         let mut torque = Vector3::new(0., 0., 0.);
-        for (i, d) in sim.dipoles.iter().enumerate() {
+        for (i, (d, (position, orientation))) in sim.dipoles.iter().zip(states).enumerate() {
             if i == index {
                 continue;
             }
+            if sim.model == Model::Point {
+                let source = d.dipole_moment(*orientation);
+                let (f, t) = point_dipole_force_torque(*position, source, r, self.dipole_moment(o));
+                force += f;
+                torque += t;
+                continue;
+            }
             let dst_positive = r + o * self.offset;
             let dst_negative = r - o * self.offset;
 
-            let src_positive = d.position + d.orientation * d.offset;
-            let src_negative = d.position - d.orientation * d.offset;
+            let src_positive = position + orientation * d.offset;
+            let src_negative = position - orientation * d.offset;
 
             let interaction = coulomb(src_negative, -d.charge, dst_negative, -self.charge);
             force += interaction;
             torque += (dst_negative - r).cross(&interaction);
-            /* let mag_torque_r = (dst_negative - r).magnitude();
-            web_sys::console::log_1(
-                &format!(
-                    "magnitude of the torque should equal displacement {}",
-                    mag_torque_r
-                )
-                .into(),
-            ); */
             let interaction = coulomb(src_positive, d.charge, dst_negative, -self.charge);
             force += interaction;
             torque += (dst_negative - r).cross(&interaction);
@@ -116,8 +179,17 @@ impl Dipole {
             force += interaction;
             torque += (dst_positive - r).cross(&interaction);
         }
+
+        // A uniform field pulls equally and oppositely on the two charges, so it only exerts a
+        // torque
+        torque += 2. * (o * self.offset).cross(&(sim.external_field * self.charge));
+
         (force, torque)
     }
+    /// Dipole moment when pointing along `orientation`, from the charges at either end
+    fn dipole_moment(&self, orientation: Vector3<f64>) -> Vector3<f64> {
+        2. * self.charge * self.offset * orientation
+    }
     fn set_offset(&mut self, offset: f64) {
         if offset <= 0. {
             return;
@@ -125,6 +197,39 @@ impl Dipole {
         self.offset = offset;
         self.moment = self.mass * offset * offset;
     }
+    /// Change the state the dipole resets to. Mass, charge and offset aren't part of that state,
+    /// so they change now.
+    fn edit_initial(&mut self, edit: Edit) {
+        match edit {
+            Edit::Position(position) => self._position = position,
+            Edit::Velocity(velocity) => self._velocity = velocity,
+            Edit::Orientation(orientation) => self._orientation = orientation.normalize(),
+            Edit::AngularVelocity(angular_velocity) => self._angular_velocity = angular_velocity,
+            Edit::Mass(_) | Edit::Charge(_) | Edit::Offset(_) => self.edit(edit),
+        }
+    }
+    fn edit(&mut self, edit: Edit) {
+        match edit {
+            Edit::Mass(mass) => {
+                self.mass = mass;
+                self.moment = mass * self.offset * self.offset;
+            }
+            Edit::Charge(charge) => self.charge = charge,
+            Edit::Offset(offset) => self.set_offset(offset),
+            Edit::Position(position) => self.position = position,
+            Edit::Velocity(velocity) => self.velocity = velocity,
+            Edit::Orientation(orientation) => self.orientation = orientation.normalize(),
+            Edit::AngularVelocity(angular_velocity) => self.angular_velocity = angular_velocity,
+        }
+        self.charges = Dipole::constituents(
+            self.mass,
+            self.position,
+            self.velocity,
+            self.orientation,
+            self.charge,
+            self.offset,
+        );
+    }
 }
 
 impl Object for Dipole {
@@ -152,8 +257,22 @@ impl Object for Dipole {
     fn get_offset(&self) -> f64 {
         self.offset
     }
+    fn get_velocity(&self) -> Vector3<f64> {
+        self.velocity
+    }
+    fn get_angular_velocity(&self) -> Vector3<f64> {
+        self.angular_velocity
+    }
+    fn get_mass(&self) -> f64 {
+        self.mass
+    }
+    fn get_charge(&self) -> f64 {
+        self.charge
+    }
 }
 
+/// A point charge
+#[derive(Clone)]
 pub struct Charge {
     mass: f64,
     position: Vector3<f64>,
@@ -161,7 +280,12 @@ pub struct Charge {
     charge: f64,
 }
 
-fn coulomb(source: Vector3<f64>, source_q: f64, dest: Vector3<f64>, dest_q: f64) -> Vector3<f64> {
+pub(crate) fn coulomb(
+    source: Vector3<f64>,
+    source_q: f64,
+    dest: Vector3<f64>,
+    dest_q: f64,
+) -> Vector3<f64> {
     let r: Vector3<f64> = dest - source;
     let r_mag = r.magnitude();
     let r_hat = r / r_mag;
@@ -169,17 +293,87 @@ fn coulomb(source: Vector3<f64>, source_q: f64, dest: Vector3<f64>, dest_q: f64)
     K * source_q * dest_q * r_hat / (r_mag * r_mag)
 }
 
+/// Field at `r` of an ideal dipole of `moment` at `source`
+fn point_dipole_field(source: Vector3<f64>, moment: Vector3<f64>, r: Vector3<f64>) -> Vector3<f64> {
+    let r = r - source;
+    let r_mag = r.magnitude();
+    let r_hat = r / r_mag;
+
+    K * (3. * moment.dot(&r_hat) * r_hat - moment) / r_mag.powi(3)
+}
+
+/// Force and torque on an ideal dipole of `moment` at `dest` from one of `source_moment` at
+/// `source`
+fn point_dipole_force_torque(
+    source: Vector3<f64>,
+    source_moment: Vector3<f64>,
+    dest: Vector3<f64>,
+    moment: Vector3<f64>,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let r = dest - source;
+    let r_mag = r.magnitude();
+    let r_hat = r / r_mag;
+    let (source_along, along) = (source_moment.dot(&r_hat), moment.dot(&r_hat));
+
+    let force = 3. * K / r_mag.powi(4)
+        * (source_along * moment + along * source_moment + source_moment.dot(&moment) * r_hat
+            - 5. * source_along * along * r_hat);
+    let torque = moment.cross(&point_dipole_field(source, source_moment, dest));
+    (force, torque)
+}
+
+/// Potential energy between ideal dipoles of `first` and `second` moment, `r` apart
+fn point_dipole_energy(r: Vector3<f64>, first: Vector3<f64>, second: Vector3<f64>) -> f64 {
+    let r_mag = r.magnitude();
+    let r_hat = r / r_mag;
+
+    K * (first.dot(&second) - 3. * first.dot(&r_hat) * second.dot(&r_hat)) / r_mag.powi(3)
+}
+
 impl Charge {
-    // calculate force on Object based on position
-    fn force(&self, r: Vector3<f64>, sim: &ChargeSimulation, index: usize) -> Vector3<f64> {
+    pub(crate) fn new(
+        mass: f64,
+        position: Vector3<f64>,
+        velocity: Vector3<f64>,
+        charge: f64,
+    ) -> Charge {
+        Charge {
+            mass,
+            position,
+            velocity,
+            charge,
+        }
+    }
+
+    fn edit(&mut self, edit: Edit) -> Result<(), String> {
+        match edit {
+            Edit::Mass(mass) => self.mass = mass,
+            Edit::Charge(charge) => self.charge = charge,
+            Edit::Position(position) => self.position = position,
+            Edit::Velocity(velocity) => self.velocity = velocity,
+            Edit::Offset(_) | Edit::Orientation(_) | Edit::AngularVelocity(_) => {
+                return Err(format!("a point charge has no {}", edit.name()))
+            }
+        }
+        Ok(())
+    }
+
+    // calculate force on the `index`th charge of `sim` when the charges are at `positions`
+    fn force(
+        &self,
+        index: usize,
+        positions: &[Vector3<f64>],
+        sim: &ChargeSimulation,
+    ) -> Vector3<f64> {
+        let r = positions[index];
         let mut force = Vector3::new(0., 0., 0.);
-        for (i, c) in sim.charges.iter().enumerate() {
+        for (i, (c, position)) in sim.charges.iter().zip(positions).enumerate() {
             if i == index {
                 continue;
             }
-            force += coulomb(c.position, c.charge, r, self.charge);
+            force += coulomb(*position, c.charge, r, self.charge);
         }
-        force
+        force + sim.external_field * self.charge
     }
 }
 
@@ -188,8 +382,8 @@ impl Object for Charge {
         &mut self,
         d_pos: Vector3<f64>,
         d_vel: Vector3<f64>,
-        d_orient: Vector3<f64>,
-        d_ang_vel: Vector3<f64>,
+        _d_orient: Vector3<f64>,
+        _d_ang_vel: Vector3<f64>,
     ) {
         self.position += d_pos;
         self.velocity += d_vel;
@@ -206,84 +400,312 @@ impl Object for Charge {
     fn get_offset(&self) -> f64 {
         0.
     }
+    fn get_velocity(&self) -> Vector3<f64> {
+        self.velocity
+    }
+    fn get_angular_velocity(&self) -> Vector3<f64> {
+        Vector3::zeros()
+    }
+    fn get_mass(&self) -> f64 {
+        self.mass
+    }
+    fn get_charge(&self) -> f64 {
+        self.charge
+    }
 }
 
+/// A system of objects that can be stepped forward in time and measured
 pub trait Simulatable {
+    /// Return every object to its initial state
     fn reset(&mut self);
+    /// Advance the simulation by `dt` seconds of simulated time
     fn update(&mut self, dt: f64);
+    /// Set the distance from the centre to each charge of every dipole
     fn set_offset(&mut self, offset: f64);
+    /// Choose the numerical method used by `update`
+    fn set_integrator(&mut self, integrator: Integrator);
+    /// Choose how dipoles act on each other. Point charges act the same under every model.
+    fn set_model(&mut self, model: Model);
+    /// Set the uniform field applied on top of the field of the objects themselves
+    fn set_external_field(&mut self, field: Vector3<f64>);
+    /// The uniform field applied on top of the field of the objects themselves
+    fn get_external_field(&self) -> Vector3<f64>;
+    /// Every object in the simulation
     fn get_objects(&self) -> Vec<&dyn Object>;
+    /// The point charges making up the objects
     fn get_charges(&self) -> Vec<&Charge>;
+    /// The electric field at `r`, including the external field
     fn get_field(&self, r: Vector3<f64>) -> Vector3<f64>;
+    /// Total linear momentum
     fn get_total_momentum(&self) -> Vector3<f64>;
+    /// Total angular momentum about the origin
     fn get_total_angular_momentum(&self) -> Vector3<f64>;
-    fn get_total_energy(&self) -> f64;
+    /// Total energy split into its kinetic and potential parts
+    fn get_energy_breakdown(&self) -> EnergyBreakdown;
+    /// Force and torque on every object, in the order of `get_objects`
+    fn get_forces(&self) -> Vec<(Vector3<f64>, Vector3<f64>)>;
+    /// Change one value of the `index`th object. Mass, charge and offset also hold after a reset;
+    /// the motion is only changed until the next reset.
+    fn edit_object(&mut self, index: usize, edit: Edit) -> Result<(), String>;
+    /// Change one value of the state the `index`th object resets to
+    fn edit_initial(&mut self, index: usize, edit: Edit) -> Result<(), String>;
+    /// Add an object starting from `object`, which is also the state it resets to, and return its
+    /// index
+    fn add_object(&mut self, object: &ObjectSpec) -> Result<usize, String>;
+    /// Whether `add_object` takes objects of `kind`, and why not if it doesn't
+    fn accepts(&self, kind: Objects) -> Result<(), String>;
+    /// Remove the `index`th object. Later objects move down one place.
+    fn remove_object(&mut self, index: usize) -> Result<(), String>;
+    /// Total energy
+    fn get_total_energy(&self) -> f64 {
+        self.get_energy_breakdown().total()
+    }
+}
+
+/// Why a dipole can't join point charges or the other way round
+const MIXED_KINDS: &str = "dipoles and point charges are simulated separately";
+
+/// A change to one value of an object
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edit {
+    /// Total mass. Must be positive.
+    Mass(f64),
+    /// The charge of a point charge, or the magnitude of each charge of a dipole
+    Charge(f64),
+    /// Distance from the centre to each charge of a dipole. Must be positive.
+    Offset(f64),
+    /// Position of the centre of mass
+    Position(Vector3<f64>),
+    /// Velocity of the centre of mass
+    Velocity(Vector3<f64>),
+    /// Direction from the negative to the positive charge of a dipole. Normalized when applied.
+    Orientation(Vector3<f64>),
+    /// Angular velocity of a dipole about its centre of mass
+    AngularVelocity(Vector3<f64>),
+}
+
+impl Edit {
+    /// The name of the value being changed
+    pub fn name(&self) -> &'static str {
+        match self {
+            Edit::Mass(_) => "mass",
+            Edit::Charge(_) => "charge",
+            Edit::Offset(_) => "offset",
+            Edit::Position(_) => "position",
+            Edit::Velocity(_) => "velocity",
+            Edit::Orientation(_) => "orientation",
+            Edit::AngularVelocity(_) => "angular velocity",
+        }
+    }
+
+    /// Reject values no object could have
+    fn validate(&self) -> Result<(), String> {
+        let valid = match *self {
+            Edit::Mass(value) | Edit::Offset(value) => value.is_finite() && value > 0.,
+            Edit::Charge(value) => value.is_finite(),
+            Edit::Orientation(value) => value.iter().all(|x| x.is_finite()) && value.norm() > 0.,
+            Edit::Position(value) | Edit::Velocity(value) | Edit::AngularVelocity(value) => {
+                value.iter().all(|x| x.is_finite())
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("invalid {}: {:?}", self.name(), self))
+        }
+    }
+}
+
+/// The energy of a simulation split by where it is stored
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EnergyBreakdown {
+    /// Kinetic energy of the centres of mass
+    pub translational: f64,
+    /// Kinetic energy of rotation about the centres of mass
+    pub rotational: f64,
+    /// Potential energy between the two charges of each dipole
+    pub intra_dipole: f64,
+    /// Potential energy between charges belonging to different objects
+    pub inter_object: f64,
+    /// Potential energy of the charges in the external field
+    pub external: f64,
+}
+
+impl EnergyBreakdown {
+    /// Translational plus rotational kinetic energy
+    pub fn kinetic(&self) -> f64 {
+        self.translational + self.rotational
+    }
+
+    /// Intra-dipole, inter-object and external field potential energy
+    pub fn potential(&self) -> f64 {
+        self.intra_dipole + self.inter_object + self.external
+    }
+
+    /// Kinetic plus potential energy
+    pub fn total(&self) -> f64 {
+        self.kinetic() + self.potential()
+    }
 }
 
+/// Electrostatic potential energy of a set of point charges, each tagged with the index of the
+/// object it belongs to. Returns the energy between charges of the same object and between charges
+/// of different objects separately.
+pub(crate) fn potential_energy(charges: &[(usize, Vector3<f64>, f64)]) -> (f64, f64) {
+    let mut intra = 0.;
+    let mut inter = 0.;
+    for (i, (object, position, charge)) in charges.iter().enumerate() {
+        for (other_object, other_position, other_charge) in charges.iter().skip(i + 1) {
+            let energy = K * charge * other_charge / (position - other_position).norm();
+            if object == other_object {
+                intra += energy;
+            } else {
+                inter += energy;
+            }
+        }
+    }
+    (intra, inter)
+}
+
+/// Potential energy of a set of point charges, tagged as for `potential_energy`, in a uniform field
+pub(crate) fn external_energy(charges: &[(usize, Vector3<f64>, f64)], field: Vector3<f64>) -> f64 {
+    charges
+        .iter()
+        .map(|(_, position, charge)| -charge * field.dot(position))
+        .sum()
+}
+
+/// Point charges interacting through Coulomb's law
 pub struct ChargeSimulation {
     charges: Vec<Charge>,
+    initial: Vec<Charge>,
+    integrator: Integrator,
+    external_field: Vector3<f64>,
 }
 
 impl ChargeSimulation {
+    /// Two opposite charges moving past each other
     pub fn new() -> Self {
+        Self::from_charges(vec![
+            Charge {
+                mass: 1.,
+                position: Vector3::new(0., 0., 0.),
+                velocity: Vector3::new(-0.5, 0., 0.),
+                charge: 5.0,
+            },
+            Charge {
+                mass: 1.,
+                position: Vector3::new(0., 10., 0.),
+                velocity: Vector3::new(0.5, 0., 0.),
+                charge: -5.0,
+            },
+        ])
+    }
+
+    pub(crate) fn from_charges(charges: Vec<Charge>) -> Self {
         Self {
-            charges: vec![
-                Charge {
-                    mass: 1.,
-                    position: Vector3::new(0., 0., 0.),
-                    velocity: Vector3::new(-0.5, 0., 0.),
-                    charge: 5.0,
-                },
-                Charge {
-                    mass: 1.,
-                    position: Vector3::new(0., 10., 0.),
-                    velocity: Vector3::new(0.5, 0., 0.),
-                    charge: -5.0,
-                },
-            ],
+            initial: charges.clone(),
+            charges,
+            integrator: Integrator::default(),
+            external_field: Vector3::zeros(),
+        }
+    }
+
+    /// Acceleration of every charge when they are at `positions`
+    fn accelerations_at(&self, positions: &[Vector3<f64>]) -> Vec<Vector3<f64>> {
+        self.charges
+            .iter()
+            .enumerate()
+            .map(|(index, charge)| charge.force(index, positions, self) / charge.mass)
+            .collect()
+    }
+
+    fn accelerations(&self) -> Vec<Vector3<f64>> {
+        let positions: Vec<_> = self.charges.iter().map(|c| c.position).collect();
+        self.accelerations_at(&positions)
+    }
+
+    fn step_euler(&mut self, dt: f64) {
+        let accelerations = self.accelerations();
+        for (charge, a) in self.charges.iter_mut().zip(accelerations) {
+            let d_vel = a * dt;
+            let d_pos = (charge.velocity + d_vel) * dt;
+            charge.update(d_pos, d_vel, Vector3::zeros(), Vector3::zeros());
+        }
+    }
+
+    fn step_verlet(&mut self, dt: f64) {
+        let accelerations = self.accelerations();
+        for (charge, a) in self.charges.iter_mut().zip(accelerations) {
+            let d_vel = a * dt / 2.;
+            let d_pos = (charge.velocity + d_vel) * dt;
+            charge.update(d_pos, d_vel, Vector3::zeros(), Vector3::zeros());
+        }
+        let accelerations = self.accelerations();
+        for (charge, a) in self.charges.iter_mut().zip(accelerations) {
+            charge.update(
+                Vector3::zeros(),
+                a * dt / 2.,
+                Vector3::zeros(),
+                Vector3::zeros(),
+            );
+        }
+    }
+
+    // runga kutta 4, with every charge advanced through each stage together
+    fn step_rk4(&mut self, dt: f64) {
+        let positions: Vec<_> = self.charges.iter().map(|c| c.position).collect();
+        let velocities: Vec<_> = self.charges.iter().map(|c| c.velocity).collect();
+
+        // Velocity and acceleration of every charge after moving at the rates `k` for `h`
+        let stage = |k: &[(Vector3<f64>, Vector3<f64>)], h: f64| {
+            let moved: Vec<_> = positions
+                .iter()
+                .zip(k)
+                .map(|(r, (v, _))| r + v * h)
+                .collect();
+            let velocities = velocities.iter().zip(k).map(|(v, (_, a))| v + a * h);
+            velocities
+                .zip(self.accelerations_at(&moved))
+                .collect::<Vec<_>>()
+        };
+
+        let k1: Vec<_> = velocities
+            .iter()
+            .copied()
+            .zip(self.accelerations())
+            .collect();
+        let k2 = stage(&k1, dt / 2.);
+        let k3 = stage(&k2, dt / 2.);
+        let k4 = stage(&k3, dt);
+
+        for (i, charge) in self.charges.iter_mut().enumerate() {
+            let d_pos = (k1[i].0 + 2. * k2[i].0 + 2. * k3[i].0 + k4[i].0) * dt / 6.;
+            let d_vel = (k1[i].1 + 2. * k2[i].1 + 2. * k3[i].1 + k4[i].1) * dt / 6.;
+            charge.update(d_pos, d_vel, Vector3::zeros(), Vector3::zeros());
         }
     }
 }
 
+impl Default for ChargeSimulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Simulatable for ChargeSimulation {
-    fn reset(&mut self) {}
+    fn reset(&mut self) {
+        self.charges = self.initial.clone();
+    }
     fn update(&mut self, dt: f64) {
-        // niave approach
-        /* for charge in 0..self.charges.len() {
-            let mut force = Vector3::zeros();
-            for other in 0..self.charges.len() {
-                if charge == other {
-                    continue;
-                }
-                let charge = &self.charges[charge];
-                let other = &self.charges[other];
-                let r = charge.position - other.position;
-                let r2 = r.norm_squared();
-                let r3 = r2 * r.norm();
-                force += r * (charge.charge * other.charge / r3);
-            }
-            let vel = self.charges[charge].velocity;
-            self.charges[charge].update(vel * dt, force * dt);
-        } */
-        // runga kutta 4
-        for index in 0..self.charges.len() {
-            let charge = &self.charges[index];
-            let k1v = charge.force(charge.position, self, index) / charge.mass;
-            let k1r = charge.velocity;
-
-            let k2v = charge.force(charge.position + k1r * dt / 2., self, index) / charge.mass;
-            let k2r = charge.velocity + k1v * dt / 2.;
-
-            let k3v = charge.force(charge.position + k2r * dt / 2., self, index) / charge.mass;
-            let k3r = charge.velocity + k2v * dt / 2.;
-
-            let k4v = charge.force(charge.position + k3r * dt, self, index) / charge.mass;
-            let k4r = charge.velocity + k3v * dt;
-
-            let d_vel = (k1v + 2. * k2v + 2. * k3v + k4v) * dt / 6.;
-            let d_pos = (k1r + 2. * k2r + 2. * k3r + k4r) * dt / 6.;
-            let charge = &mut self.charges[index];
-            charge.update(d_pos, d_vel, Vector3::zeros(), Vector3::zeros());
+        if dt == 0. {
+            return;
+        }
+
+        match self.integrator {
+            Integrator::Euler => self.step_euler(dt),
+            Integrator::Verlet => self.step_verlet(dt),
+            Integrator::Rk4 => self.step_rk4(dt),
         }
     }
     fn get_objects(&self) -> Vec<&dyn Object> {
@@ -293,7 +715,7 @@ impl Simulatable for ChargeSimulation {
         self.charges.iter().collect()
     }
     fn get_field(&self, r: Vector3<f64>) -> Vector3<f64> {
-        let mut field = Vector3::zeros();
+        let mut field = self.external_field;
         for c in self.charges.iter() {
             field += coulomb(c.position, c.charge, r, 1.);
         }
@@ -315,58 +737,271 @@ impl Simulatable for ChargeSimulation {
         total
     }
 
-    fn get_total_energy(&self) -> f64 {
-        let mut total = 0.;
+    fn get_energy_breakdown(&self) -> EnergyBreakdown {
+        let mut translational = 0.;
         for charge in self.charges.iter() {
-            total += charge.velocity.norm_squared() * charge.mass / 2.;
+            translational += charge.velocity.norm_squared() * charge.mass / 2.;
         }
-        for i in 0..self.charges.len() - 1 {
-            let c = &self.charges[i];
-            for i in (i + 1)..self.charges.len() {
-                let other = &self.charges[i];
-                total += K * c.charge * other.charge / (c.position - other.position).norm();
+
+        let charges = self
+            .charges
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, c.position, c.charge))
+            .collect::<Vec<_>>();
+        let (intra_dipole, inter_object) = potential_energy(&charges);
+
+        EnergyBreakdown {
+            translational,
+            rotational: 0.,
+            intra_dipole,
+            inter_object,
+            external: external_energy(&charges, self.external_field),
+        }
+    }
+
+    fn set_offset(&mut self, _offset: f64) {}
+
+    fn set_model(&mut self, _model: Model) {}
+
+    fn get_forces(&self) -> Vec<(Vector3<f64>, Vector3<f64>)> {
+        let positions: Vec<_> = self.charges.iter().map(|c| c.position).collect();
+        self.charges
+            .iter()
+            .enumerate()
+            .map(|(index, charge)| (charge.force(index, &positions, self), Vector3::zeros()))
+            .collect()
+    }
+    fn edit_initial(&mut self, index: usize, edit: Edit) -> Result<(), String> {
+        edit.validate()?;
+        self.initial
+            .get_mut(index)
+            .ok_or_else(|| format!("no object {}", index))?
+            .edit(edit)?;
+        if let Edit::Mass(_) | Edit::Charge(_) = edit {
+            self.charges[index].edit(edit)?;
+        }
+        Ok(())
+    }
+    fn add_object(&mut self, object: &ObjectSpec) -> Result<usize, String> {
+        self.accepts(object.kind)?;
+        object.check()?;
+        self.charges.push(object.point_charge());
+        self.initial.push(object.point_charge());
+        Ok(self.charges.len() - 1)
+    }
+    fn accepts(&self, kind: Objects) -> Result<(), String> {
+        match kind {
+            Objects::Charge => Ok(()),
+            Objects::Dipole => Err(MIXED_KINDS.to_string()),
+        }
+    }
+    fn remove_object(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.charges.len() {
+            return Err(format!("no object {}", index));
+        }
+        self.charges.remove(index);
+        self.initial.remove(index);
+        Ok(())
+    }
+    fn edit_object(&mut self, index: usize, edit: Edit) -> Result<(), String> {
+        edit.validate()?;
+        let charge = self
+            .charges
+            .get_mut(index)
+            .ok_or_else(|| format!("no object {}", index))?;
+        charge.edit(edit)?;
+        if let Edit::Mass(_) | Edit::Charge(_) = edit {
+            if let Some(initial) = self.initial.get_mut(index) {
+                initial.edit(edit)?;
             }
         }
-        total
+        Ok(())
     }
 
-    fn set_offset(&mut self, offset: f64) {}
+    fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+    fn set_external_field(&mut self, field: Vector3<f64>) {
+        self.external_field = field;
+    }
+    fn get_external_field(&self) -> Vector3<f64> {
+        self.external_field
+    }
 }
 
+/// Rigid dipoles interacting through Coulomb's law between their charges
 pub struct DipoleSimulation {
     dipoles: Vec<Dipole>,
+    integrator: Integrator,
+    model: Model,
+    external_field: Vector3<f64>,
 }
 
-static K: f64 = 2.0;
+pub(crate) static K: f64 = 2.0;
 
 impl DipoleSimulation {
-    pub(crate) fn new(mass1: f64, mass2: f64, charge1: f64, charge2: f64) -> DipoleSimulation {
+    /// Two perpendicular dipoles at rest, ten units apart along the x axis
+    pub fn new(mass1: f64, mass2: f64, charge1: f64, charge2: f64) -> DipoleSimulation {
+        DipoleSimulation::from_dipoles(vec![
+            Dipole::new(
+                mass1,
+                Vector3::zeros(),
+                Vector3::zeros(),
+                Vector3::new(1., 0., 0.),
+                Vector3::zeros(),
+                charge1,
+                0.1,
+            ),
+            Dipole::new(
+                mass2,
+                Vector3::new(10., 0., 0.),
+                Vector3::zeros(),
+                Vector3::new(0., 1., 0.),
+                Vector3::zeros(),
+                charge2,
+                0.1,
+            ),
+        ])
+    }
+
+    pub(crate) fn from_dipoles(dipoles: Vec<Dipole>) -> DipoleSimulation {
         DipoleSimulation {
-            dipoles: vec![
-                Dipole::new(
-                    mass1,
-                    Vector3::zeros(),
-                    Vector3::zeros(),
-                    Vector3::new(1., 0., 0.),
-                    Vector3::zeros(),
-                    charge1,
-                    0.1,
-                ),
-                Dipole::new(
-                    mass2,
-                    Vector3::new(10., 0., 0.),
-                    Vector3::zeros(),
-                    Vector3::new(0., 1., 0.),
-                    Vector3::zeros(),
-                    charge2,
-                    0.1,
-                ),
-            ],
+            dipoles,
+            integrator: Integrator::default(),
+            model: Model::default(),
+            external_field: Vector3::zeros(),
+        }
+    }
+
+    /// Linear and angular acceleration of every dipole when they have the positions and
+    /// orientations given in `states`
+    fn accelerations_at(
+        &self,
+        states: &[(Vector3<f64>, Vector3<f64>)],
+    ) -> Vec<(Vector3<f64>, Vector3<f64>)> {
+        self.dipoles
+            .iter()
+            .enumerate()
+            .map(|(index, dipole)| {
+                let (force, torque) = dipole.force_torque(index, states, self);
+                (force / dipole.mass, torque / dipole.moment)
+            })
+            .collect()
+    }
+
+    /// Linear and angular acceleration of every dipole in its current state
+    fn accelerations(&self) -> Vec<(Vector3<f64>, Vector3<f64>)> {
+        let states: Vec<_> = self
+            .dipoles
+            .iter()
+            .map(|d| (d.position, d.orientation))
+            .collect();
+        self.accelerations_at(&states)
+    }
+
+    fn step_euler(&mut self, dt: f64) {
+        let accelerations = self.accelerations();
+        for (dipole, (a, alpha)) in self.dipoles.iter_mut().zip(accelerations) {
+            let d_vel = a * dt;
+            let d_ang_vel = alpha * dt;
+            let d_pos = (dipole.velocity + d_vel) * dt;
+            let d_orient = (dipole.angular_velocity + d_ang_vel) * dt;
+            dipole.update(d_pos, d_vel, d_orient, d_ang_vel);
+        }
+    }
+
+    fn step_verlet(&mut self, dt: f64) {
+        let accelerations = self.accelerations();
+        for (dipole, (a, alpha)) in self.dipoles.iter_mut().zip(accelerations) {
+            let d_vel = a * dt / 2.;
+            let d_ang_vel = alpha * dt / 2.;
+            let d_pos = (dipole.velocity + d_vel) * dt;
+            let d_orient = (dipole.angular_velocity + d_ang_vel) * dt;
+            dipole.update(d_pos, d_vel, d_orient, d_ang_vel);
+        }
+        let accelerations = self.accelerations();
+        for (dipole, (a, alpha)) in self.dipoles.iter_mut().zip(accelerations) {
+            dipole.update(
+                Vector3::zeros(),
+                a * dt / 2.,
+                Vector3::zeros(),
+                alpha * dt / 2.,
+            );
+        }
+    }
+
+    // runga kutta 4, with every dipole advanced through each stage together
+    fn step_rk4(&mut self, dt: f64) {
+        let states: Vec<_> = self
+            .dipoles
+            .iter()
+            .map(|d| (d.position, d.orientation))
+            .collect();
+        let velocities: Vec<_> = self
+            .dipoles
+            .iter()
+            .map(|d| (d.velocity, d.angular_velocity))
+            .collect();
+
+        // Rates of change of every dipole after moving at the rates `k` for `h`
+        let stage = |k: &[Rate], h: f64| {
+            let moved: Vec<_> = states
+                .iter()
+                .zip(k)
+                .map(|((r, o), k)| (r + k.velocity * h, rotate(*o, k.angular_velocity * h)))
+                .collect();
+            let accelerations = self.accelerations_at(&moved);
+            velocities
+                .iter()
+                .zip(k)
+                .zip(accelerations)
+                .map(|(((v, w), k), (a, alpha))| Rate {
+                    velocity: v + k.acceleration * h,
+                    angular_velocity: w + k.angular_acceleration * h,
+                    acceleration: a,
+                    angular_acceleration: alpha,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let k1: Vec<_> = velocities
+            .iter()
+            .zip(self.accelerations())
+            .map(|((v, w), (a, alpha))| Rate {
+                velocity: *v,
+                angular_velocity: *w,
+                acceleration: a,
+                angular_acceleration: alpha,
+            })
+            .collect();
+        let k2 = stage(&k1, dt / 2.);
+        let k3 = stage(&k2, dt / 2.);
+        let k4 = stage(&k3, dt);
+
+        for (i, dipole) in self.dipoles.iter_mut().enumerate() {
+            let weighted = |rate: fn(&Rate) -> Vector3<f64>| {
+                (rate(&k1[i]) + 2. * rate(&k2[i]) + 2. * rate(&k3[i]) + rate(&k4[i])) * dt / 6.
+            };
+            dipole.update(
+                weighted(|k| k.velocity),
+                weighted(|k| k.acceleration),
+                weighted(|k| k.angular_velocity),
+                weighted(|k| k.angular_acceleration),
+            );
         }
     }
 }
 
-fn rotate(orientation: Vector3<f64>, omega: Vector3<f64>) -> Vector3<f64> {
+/// Rate of change of the state of one dipole, for the Runge Kutta stages
+struct Rate {
+    velocity: Vector3<f64>,
+    angular_velocity: Vector3<f64>,
+    acceleration: Vector3<f64>,
+    angular_acceleration: Vector3<f64>,
+}
+
+pub(crate) fn rotate(orientation: Vector3<f64>, omega: Vector3<f64>) -> Vector3<f64> {
     let rotation = nalgebra::Rotation3::from_scaled_axis(omega);
     rotation * orientation
 }
@@ -382,58 +1017,10 @@ impl Simulatable for DipoleSimulation {
             return;
         }
 
-        web_sys::console::log_1(&format!("dt: {}", dt).into());
-
-        for index in 0..self.dipoles.len() {
-            let dipole = &self.dipoles[index];
-            let (k1v, l1v) = dipole.force_torque(dipole.position, dipole.orientation, self, index);
-            let k1v = k1v / dipole.mass;
-            let l1v = l1v / dipole.moment;
-            let k1r = dipole.velocity;
-            let l1r = dipole.angular_velocity;
-
-            let rotation = l1v * dt / 2.;
-            let (k2v, l2v) = dipole.force_torque(
-                dipole.position + k1r * dt / 2.,
-                rotate(dipole.orientation, rotation),
-                self,
-                index,
-            );
-            let k2v = k2v / dipole.mass;
-            let l2v = l2v / dipole.moment;
-            let k2r = dipole.velocity + k1v * dt / 2.;
-            let l2r = dipole.angular_velocity + l1v * dt / 2.;
-
-            let rotation = l2v * dt / 2.;
-            let (k3v, l3v) = dipole.force_torque(
-                dipole.position + k2r * dt / 2.,
-                rotate(dipole.orientation, rotation),
-                self,
-                index,
-            );
-            let k3v = k3v / dipole.mass;
-            let l3v = l3v / dipole.moment;
-            let k3r = dipole.velocity + k2v * dt / 2.;
-            let l3r = dipole.angular_velocity + l2v * dt / 2.;
-
-            let rotation = l3v * dt;
-            let (k4v, l4v) = dipole.force_torque(
-                dipole.position + k3r * dt,
-                rotate(dipole.orientation, rotation),
-                self,
-                index,
-            );
-            let k4v = k4v / dipole.mass;
-            let l4v = l4v / dipole.moment;
-            let k4r = dipole.velocity + k3v * dt;
-            let l4r = dipole.angular_velocity + l3v * dt;
-
-            let d_vel = (k1v + 2. * k2v + 2. * k3v + k4v) * dt / 6.;
-            let d_pos = (k1r + 2. * k2r + 2. * k3r + k4r) * dt / 6.;
-            let d_ang_vel = (l1v + 2. * l2v + 2. * l3v + l4v) * dt / 6.;
-            let d_orient = (l1r + 2. * l2r + 2. * l3r + l4r) * dt / 6.;
-            let dipole = &mut self.dipoles[index];
-            dipole.update(d_pos, d_vel, d_orient, d_ang_vel);
+        match self.integrator {
+            Integrator::Euler => self.step_euler(dt),
+            Integrator::Verlet => self.step_verlet(dt),
+            Integrator::Rk4 => self.step_rk4(dt),
         }
     }
     fn get_objects(&self) -> Vec<&dyn Object> {
@@ -442,13 +1029,16 @@ impl Simulatable for DipoleSimulation {
     fn get_charges(&self) -> Vec<&Charge> {
         self.dipoles
             .iter()
-            .map(|d| vec![&d.charges[0], &d.charges[1]])
-            .flatten()
+            .flat_map(|d| vec![&d.charges[0], &d.charges[1]])
             .collect()
     }
     fn get_field(&self, r: Vector3<f64>) -> Vector3<f64> {
-        let mut field = Vector3::zeros();
+        let mut field = self.external_field;
         for d in self.dipoles.iter() {
+            if self.model == Model::Point {
+                field += point_dipole_field(d.position, d.dipole_moment(d.orientation), r);
+                continue;
+            }
             field += coulomb(d.position - d.orientation * d.offset, -d.charge, r, 1.);
             field += coulomb(d.position + d.orientation * d.offset, d.charge, r, 1.);
         }
@@ -469,45 +1059,110 @@ impl Simulatable for DipoleSimulation {
         }
         total
     }
-    fn get_total_energy(&self) -> f64 {
-        let mut total = 0.;
+    fn get_energy_breakdown(&self) -> EnergyBreakdown {
+        let mut translational = 0.;
+        let mut rotational = 0.;
         for dipole in self.dipoles.iter() {
-            total += dipole.mass * dipole.velocity.norm_squared() / 2.;
-            total += dipole.moment * dipole.angular_velocity.norm_squared() / 2.;
+            translational += dipole.mass * dipole.velocity.norm_squared() / 2.;
+            rotational += dipole.moment * dipole.angular_velocity.norm_squared() / 2.;
         }
 
         let charges = self
             .dipoles
             .iter()
-            .flat_map(|d| {
+            .enumerate()
+            .flat_map(|(i, d)| {
                 vec![
-                    Charge {
-                        position: d.position - d.orientation * d.offset,
-                        charge: -d.charge,
-                        mass: 0.,
-                        velocity: Vector3::zeros(),
-                    },
-                    Charge {
-                        position: d.position + d.orientation * d.offset,
-                        charge: d.charge,
-                        mass: 0.,
-                        velocity: Vector3::zeros(),
-                    },
+                    (i, d.position - d.orientation * d.offset, -d.charge),
+                    (i, d.position + d.orientation * d.offset, d.charge),
                 ]
             })
             .collect::<Vec<_>>();
-        for i in 0..charges.len() - 1 {
-            let c = &charges[i];
-            for i in (i + 1)..charges.len() {
-                let other = &charges[i];
-                total += K * c.charge * other.charge / (c.position - other.position).norm();
+        let (intra_dipole, mut inter_object) = potential_energy(&charges);
+        if self.model == Model::Point {
+            inter_object = 0.;
+            for (i, first) in self.dipoles.iter().enumerate() {
+                for second in self.dipoles.iter().skip(i + 1) {
+                    inter_object += point_dipole_energy(
+                        second.position - first.position,
+                        first.dipole_moment(first.orientation),
+                        second.dipole_moment(second.orientation),
+                    );
+                }
             }
         }
-        total
+
+        EnergyBreakdown {
+            translational,
+            rotational,
+            intra_dipole,
+            inter_object,
+            external: external_energy(&charges, self.external_field),
+        }
     }
     fn set_offset(&mut self, offset: f64) {
         for dipole in self.dipoles.iter_mut() {
             dipole.set_offset(offset);
         }
     }
+    fn get_forces(&self) -> Vec<(Vector3<f64>, Vector3<f64>)> {
+        let states: Vec<_> = self
+            .dipoles
+            .iter()
+            .map(|d| (d.position, d.orientation))
+            .collect();
+        self.dipoles
+            .iter()
+            .enumerate()
+            .map(|(index, dipole)| dipole.force_torque(index, &states, self))
+            .collect()
+    }
+    fn edit_initial(&mut self, index: usize, edit: Edit) -> Result<(), String> {
+        edit.validate()?;
+        self.dipoles
+            .get_mut(index)
+            .ok_or_else(|| format!("no object {}", index))?
+            .edit_initial(edit);
+        Ok(())
+    }
+    fn add_object(&mut self, object: &ObjectSpec) -> Result<usize, String> {
+        self.accepts(object.kind)?;
+        object.check()?;
+        self.dipoles.push(object.dipole());
+        Ok(self.dipoles.len() - 1)
+    }
+    fn accepts(&self, kind: Objects) -> Result<(), String> {
+        match kind {
+            Objects::Dipole => Ok(()),
+            Objects::Charge => Err(MIXED_KINDS.to_string()),
+        }
+    }
+    fn remove_object(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.dipoles.len() {
+            return Err(format!("no object {}", index));
+        }
+        self.dipoles.remove(index);
+        Ok(())
+    }
+    fn edit_object(&mut self, index: usize, edit: Edit) -> Result<(), String> {
+        edit.validate()?;
+        let dipole = self
+            .dipoles
+            .get_mut(index)
+            .ok_or_else(|| format!("no object {}", index))?;
+        dipole.edit(edit);
+        Ok(())
+    }
+    fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+    fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+    fn set_external_field(&mut self, field: Vector3<f64>) {
+        self.external_field = field;
+    }
+    fn get_external_field(&self) -> Vector3<f64> {
+        self.external_field
+    }
 }
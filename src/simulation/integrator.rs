@@ -0,0 +1,48 @@
+//! The numerical methods available for stepping a simulation
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How a simulation advances its objects over one step
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    /// Semi-implicit (symplectic) Euler. Cheap, first order.
+    Euler,
+    /// Velocity Verlet. Second order and symplectic, so energy oscillates instead of drifting.
+    Verlet,
+    /// Classic fourth order Runge Kutta
+    #[default]
+    Rk4,
+}
+
+impl Integrator {
+    /// Every integrator, in the order they are offered
+    pub const ALL: [Integrator; 3] = [Integrator::Euler, Integrator::Verlet, Integrator::Rk4];
+
+    /// Short name used in files and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::Euler => "euler",
+            Integrator::Verlet => "verlet",
+            Integrator::Rk4 => "rk4",
+        }
+    }
+}
+
+impl fmt::Display for Integrator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Integrator, String> {
+        Integrator::ALL
+            .iter()
+            .find(|integrator| integrator.name() == name.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("unknown integrator '{}'", name))
+    }
+}
@@ -1 +1,13 @@
+//! The physics, independent of how it is displayed
+
+pub mod batch;
+pub mod binary_trajectory;
+pub mod comparison;
+pub mod diagnostics;
 pub mod dipole;
+pub mod integrator;
+pub mod playback;
+pub mod scenario;
+pub mod sweep;
+pub mod trajectory;
+pub mod validation;
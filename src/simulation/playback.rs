@@ -0,0 +1,172 @@
+//! Replaying recorded trajectories
+
+use crate::simulation::binary_trajectory::TrajectoryReader;
+use crate::simulation::dipole::coulomb;
+use crate::simulation::dipole::potential_energy;
+use crate::simulation::dipole::Charge;
+use crate::simulation::dipole::Edit;
+use crate::simulation::dipole::EnergyBreakdown;
+use crate::simulation::dipole::Model;
+use crate::simulation::dipole::Object;
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use crate::simulation::integrator::Integrator;
+use crate::simulation::scenario::ObjectSpec;
+use crate::simulation::trajectory::Frame;
+use nalgebra::Vector3;
+use std::io::{self, Read, Seek};
+
+/// Replays a recorded trajectory through the `Simulatable` interface so that runs produced
+/// offline can be rendered like a live simulation.
+///
+/// Advancing by `dt` moves the playback clock and shows the last frame recorded at or before it.
+pub struct Playback<R: Read + Seek> {
+    reader: TrajectoryReader<R>,
+    time: f64,
+    current: usize,
+    frame: Frame,
+}
+
+impl<R: Read + Seek> Playback<R> {
+    /// Start playback at the first frame of the trajectory
+    pub fn new(mut reader: TrajectoryReader<R>) -> io::Result<Playback<R>> {
+        let frame = reader.read_frame(0)?;
+
+        Ok(Playback {
+            reader,
+            time: frame.time,
+            current: 0,
+            frame,
+        })
+    }
+
+    /// Jump straight to a frame
+    pub fn seek(&mut self, frame: usize) -> io::Result<()> {
+        self.frame = self.reader.read_frame(frame)?;
+        self.current = frame;
+        self.time = self.frame.time;
+        Ok(())
+    }
+
+    /// Every point charge in the current frame, tagged with the index of its object
+    fn point_charges(&self) -> Vec<(usize, Vector3<f64>, f64)> {
+        self.frame
+            .objects
+            .iter()
+            .enumerate()
+            .flat_map(|(i, object)| match object.kind {
+                Objects::Dipole => vec![
+                    (
+                        i,
+                        object.position - object.orientation * object.offset,
+                        -object.charge,
+                    ),
+                    (
+                        i,
+                        object.position + object.orientation * object.offset,
+                        object.charge,
+                    ),
+                ],
+                Objects::Charge => vec![(i, object.position, object.charge)],
+            })
+            .collect()
+    }
+}
+
+impl<R: Read + Seek> Simulatable for Playback<R> {
+    fn reset(&mut self) {
+        let _ = self.seek(0);
+    }
+    fn update(&mut self, dt: f64) {
+        if dt == 0. {
+            return;
+        }
+
+        self.time += dt;
+        let frame = self.reader.frame_at_time(self.time);
+        if frame != self.current {
+            if let Ok(next) = self.reader.read_frame(frame) {
+                self.frame = next;
+                self.current = frame;
+            }
+        }
+    }
+    fn set_offset(&mut self, _offset: f64) {}
+    fn set_integrator(&mut self, _integrator: Integrator) {}
+    fn set_model(&mut self, _model: Model) {}
+    fn set_external_field(&mut self, _field: Vector3<f64>) {}
+    fn get_external_field(&self) -> Vector3<f64> {
+        Vector3::zeros()
+    }
+    fn get_objects(&self) -> Vec<&dyn Object> {
+        self.frame
+            .objects
+            .iter()
+            .map(|o| o as &dyn Object)
+            .collect()
+    }
+    fn get_charges(&self) -> Vec<&Charge> {
+        vec![]
+    }
+    fn get_field(&self, r: Vector3<f64>) -> Vector3<f64> {
+        let mut field = Vector3::zeros();
+        for (_, position, charge) in self.point_charges() {
+            field += coulomb(position, charge, r, 1.);
+        }
+        field
+    }
+    fn get_total_momentum(&self) -> Vector3<f64> {
+        let mut total = Vector3::zeros();
+        for object in self.frame.objects.iter() {
+            total += object.velocity * object.mass;
+        }
+        total
+    }
+    fn get_total_angular_momentum(&self) -> Vector3<f64> {
+        let mut total = Vector3::zeros();
+        for object in self.frame.objects.iter() {
+            let moment = object.mass * object.offset * object.offset;
+            total += moment * object.angular_velocity;
+            total += object.position.cross(&object.velocity) * object.mass;
+        }
+        total
+    }
+    fn get_forces(&self) -> Vec<(Vector3<f64>, Vector3<f64>)> {
+        // Frames record motion, not the forces behind it
+        vec![(Vector3::zeros(), Vector3::zeros()); self.frame.objects.len()]
+    }
+    fn edit_object(&mut self, _index: usize, edit: Edit) -> Result<(), String> {
+        Err(format!("can't change the {} of a recording", edit.name()))
+    }
+    fn edit_initial(&mut self, _index: usize, edit: Edit) -> Result<(), String> {
+        Err(format!("can't change the {} of a recording", edit.name()))
+    }
+    fn add_object(&mut self, _object: &ObjectSpec) -> Result<usize, String> {
+        Err("can't add objects to a recording".to_string())
+    }
+    fn accepts(&self, _kind: Objects) -> Result<(), String> {
+        Err("can't add objects to a recording".to_string())
+    }
+    fn remove_object(&mut self, _index: usize) -> Result<(), String> {
+        Err("can't remove objects from a recording".to_string())
+    }
+    fn get_energy_breakdown(&self) -> EnergyBreakdown {
+        let mut translational = 0.;
+        let mut rotational = 0.;
+        for object in self.frame.objects.iter() {
+            let moment = object.mass * object.offset * object.offset;
+            translational += object.mass * object.velocity.norm_squared() / 2.;
+            rotational += moment * object.angular_velocity.norm_squared() / 2.;
+        }
+
+        let (intra_dipole, inter_object) = potential_energy(&self.point_charges());
+
+        EnergyBreakdown {
+            translational,
+            rotational,
+            intra_dipole,
+            inter_object,
+            external: 0.,
+        }
+    }
+}
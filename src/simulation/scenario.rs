@@ -0,0 +1,223 @@
+//! Initial conditions that can be saved to and loaded from JSON.
+//!
+//! ```json
+//! {
+//!   "name": "two dipoles",
+//!   "objects": [
+//!     { "kind": "dipole", "mass": 1, "charge": 1, "offset": 0.1,
+//!       "position": [0, 0, 0], "orientation": [1, 0, 0] },
+//!     { "kind": "dipole", "mass": 1, "charge": 1, "offset": 0.1,
+//!       "position": [10, 0, 0], "orientation": [0, 1, 0] }
+//!   ]
+//! }
+//! ```
+//!
+//! `velocity` and `angular_velocity` default to zero, `orientation` to the x axis and `offset` to
+//! 0.1. Point charges ignore the orientation, offset and angular velocity. A uniform
+//! `external_field` can be applied to the whole scenario and defaults to zero.
+
+use crate::simulation::dipole::Charge;
+use crate::simulation::dipole::ChargeSimulation;
+use crate::simulation::dipole::Dipole;
+use crate::simulation::dipole::DipoleSimulation;
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// The initial state of one object
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectSpec {
+    /// What kind of object this is
+    pub kind: Objects,
+    /// Total mass
+    pub mass: f64,
+    /// The charge of a point charge, or the magnitude of each charge of a dipole
+    pub charge: f64,
+    /// Distance from the centre to each charge of a dipole
+    #[serde(default = "default_offset")]
+    pub offset: f64,
+    /// Position of the centre of mass
+    pub position: [f64; 3],
+    /// Velocity of the centre of mass
+    #[serde(default)]
+    pub velocity: [f64; 3],
+    /// Direction from the negative to the positive charge of a dipole
+    #[serde(default = "default_orientation")]
+    pub orientation: [f64; 3],
+    /// Angular velocity of a dipole about its centre of mass
+    #[serde(default)]
+    pub angular_velocity: [f64; 3],
+}
+
+impl ObjectSpec {
+    /// An object of unit mass and charge at rest at `position`, with dipoles along the x axis
+    pub fn new(kind: Objects, position: [f64; 3]) -> ObjectSpec {
+        ObjectSpec {
+            kind,
+            mass: 1.,
+            charge: 1.,
+            offset: default_offset(),
+            position,
+            velocity: [0.; 3],
+            orientation: default_orientation(),
+            angular_velocity: [0.; 3],
+        }
+    }
+
+    /// Parse a single object from JSON, in the format of the entries of a scenario
+    pub fn from_json(json: &str) -> Result<ObjectSpec, String> {
+        serde_json::from_str(json).map_err(|err| format!("invalid object: {}", err))
+    }
+
+    /// Reject objects that can't be simulated
+    pub fn check(&self) -> Result<(), String> {
+        if self.mass <= 0. {
+            return Err("must have a positive mass".to_string());
+        }
+        if self.kind == Objects::Dipole {
+            if self.offset <= 0. {
+                return Err("must have a positive offset".to_string());
+            }
+            if Vector3::from(self.orientation).norm() <= f64::EPSILON {
+                return Err("has no orientation".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The dipole starting in this state. The spec should have passed `check`.
+    pub(crate) fn dipole(&self) -> Dipole {
+        Dipole::new(
+            self.mass,
+            self.position.into(),
+            self.velocity.into(),
+            Vector3::from(self.orientation).normalize(),
+            self.angular_velocity.into(),
+            self.charge,
+            self.offset,
+        )
+    }
+
+    /// The point charge starting in this state
+    pub(crate) fn point_charge(&self) -> Charge {
+        Charge::new(
+            self.mass,
+            self.position.into(),
+            self.velocity.into(),
+            self.charge,
+        )
+    }
+}
+
+fn default_offset() -> f64 {
+    0.1
+}
+
+fn default_orientation() -> [f64; 3] {
+    [1., 0., 0.]
+}
+
+/// A set of objects and their initial state
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Human readable description
+    #[serde(default)]
+    pub name: String,
+    /// Every object, in simulation order
+    pub objects: Vec<ObjectSpec>,
+    /// Uniform field applied on top of the field of the objects
+    #[serde(default)]
+    pub external_field: [f64; 3],
+}
+
+impl Scenario {
+    /// The two perpendicular dipoles the web client starts with
+    pub fn two_dipoles() -> Scenario {
+        let dipole = |position: [f64; 3], orientation: [f64; 3]| ObjectSpec {
+            orientation,
+            ..ObjectSpec::new(Objects::Dipole, position)
+        };
+
+        Scenario {
+            name: "two dipoles".to_string(),
+            objects: vec![
+                dipole([0., 0., 0.], [1., 0., 0.]),
+                dipole([10., 0., 0.], [0., 1., 0.]),
+            ],
+            external_field: [0.; 3],
+        }
+    }
+
+    /// The current state of a simulation, so that it can be restarted from here
+    pub fn capture(simulation: &dyn Simulatable, name: &str) -> Scenario {
+        let objects = simulation
+            .get_objects()
+            .iter()
+            .map(|object| ObjectSpec {
+                kind: object.get_type(),
+                mass: object.get_mass(),
+                charge: object.get_charge(),
+                offset: match object.get_type() {
+                    Objects::Dipole => object.get_offset(),
+                    Objects::Charge => default_offset(),
+                },
+                position: object.get_pos().into(),
+                velocity: object.get_velocity().into(),
+                orientation: match object.get_type() {
+                    Objects::Dipole => object.get_orientation().into(),
+                    Objects::Charge => default_orientation(),
+                },
+                angular_velocity: object.get_angular_velocity().into(),
+            })
+            .collect();
+
+        Scenario {
+            name: name.to_string(),
+            objects,
+            external_field: simulation.get_external_field().into(),
+        }
+    }
+
+    /// Parse a scenario from JSON
+    pub fn from_json(json: &str) -> Result<Scenario, String> {
+        serde_json::from_str(json).map_err(|err| format!("invalid scenario: {}", err))
+    }
+
+    /// Serialize the scenario as pretty printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scenarios always serialize")
+    }
+
+    /// Create a simulation starting from this scenario.
+    ///
+    /// Every object has to be of the same kind, as dipoles and point charges are simulated
+    /// separately.
+    pub fn build(&self) -> Result<Box<dyn Simulatable>, String> {
+        let kind = match self.objects.first() {
+            Some(object) => object.kind,
+            None => return Err("scenario has no objects".to_string()),
+        };
+        if self.objects.iter().any(|object| object.kind != kind) {
+            return Err("scenario mixes dipoles and point charges".to_string());
+        }
+
+        for (index, object) in self.objects.iter().enumerate() {
+            object
+                .check()
+                .map_err(|err| format!("object {} {}", index, err))?;
+        }
+
+        let mut simulation: Box<dyn Simulatable> = match kind {
+            Objects::Dipole => Box::new(DipoleSimulation::from_dipoles(
+                self.objects.iter().map(ObjectSpec::dipole).collect(),
+            )),
+            Objects::Charge => Box::new(ChargeSimulation::from_charges(
+                self.objects.iter().map(ObjectSpec::point_charge).collect(),
+            )),
+        };
+        simulation.set_external_field(self.external_field.into());
+
+        Ok(simulation)
+    }
+}
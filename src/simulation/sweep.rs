@@ -0,0 +1,311 @@
+//! Repeating a scenario over a grid of parameters and collecting a table of outcomes.
+//!
+//! Cases run in parallel on native threads, so sweeps cannot be run in the browser.
+
+use crate::simulation::batch::{self, RunConfig, RunSummary};
+use crate::simulation::dipole::Objects;
+use crate::simulation::scenario::Scenario;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// A property of the scenario that a sweep varies.
+///
+/// Object properties apply to every object, or only to the object with the given index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Parameter {
+    /// Distance from the centre to each charge of a dipole
+    Offset(Option<usize>),
+    /// Magnitude of the charge. The sign of each object's charge is kept.
+    Charge(Option<usize>),
+    /// Total mass
+    Mass(Option<usize>),
+    /// Seed for a random displacement of every initial position by up to the sweep's jitter
+    Seed,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, index) = match self {
+            Parameter::Offset(index) => ("offset", index),
+            Parameter::Charge(index) => ("charge", index),
+            Parameter::Mass(index) => ("mass", index),
+            Parameter::Seed => return f.write_str("seed"),
+        };
+        match index {
+            Some(index) => write!(f, "{}[{}]", name, index),
+            None => f.write_str(name),
+        }
+    }
+}
+
+impl FromStr for Parameter {
+    type Err = String;
+
+    /// Parses `offset`, `charge`, `mass` or `seed`, optionally followed by an object index such
+    /// as `mass[1]`
+    fn from_str(text: &str) -> Result<Parameter, String> {
+        let (name, index) = match text.find('[') {
+            Some(open) if text.ends_with(']') => {
+                let index = text[open + 1..text.len() - 1]
+                    .parse()
+                    .map_err(|_| format!("invalid object index in '{}'", text))?;
+                (&text[..open], Some(index))
+            }
+            _ => (text, None),
+        };
+
+        match (name, index) {
+            ("offset", index) => Ok(Parameter::Offset(index)),
+            ("charge", index) => Ok(Parameter::Charge(index)),
+            ("mass", index) => Ok(Parameter::Mass(index)),
+            ("seed", None) => Ok(Parameter::Seed),
+            _ => Err(format!("unknown parameter '{}'", text)),
+        }
+    }
+}
+
+/// One dimension of the parameter grid
+#[derive(Clone, Debug, PartialEq)]
+pub struct Axis {
+    /// What is varied
+    pub parameter: Parameter,
+    /// Every value it takes
+    pub values: Vec<f64>,
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    /// Parses `parameter=a,b,c` for a list of values or `parameter=start:end:count` for evenly
+    /// spaced values including both ends
+    fn from_str(text: &str) -> Result<Axis, String> {
+        let mut parts = text.splitn(2, '=');
+        let parameter = parts.next().unwrap_or_default().parse()?;
+        let values = parts
+            .next()
+            .ok_or_else(|| format!("'{}' has no values", text))?;
+        let number = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid value '{}' in '{}'", value, text))
+        };
+
+        let values = if values.contains(':') {
+            let range: Vec<&str> = values.split(':').collect();
+            if range.len() != 3 {
+                return Err(format!("'{}' should be start:end:count", values));
+            }
+            let (start, end) = (number(range[0])?, number(range[1])?);
+            let count: usize = range[2]
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid count in '{}'", text))?;
+            match count {
+                0 => vec![],
+                1 => vec![start],
+                _ => (0..count)
+                    .map(|i| start + (end - start) * i as f64 / (count - 1) as f64)
+                    .collect(),
+            }
+        } else {
+            values.split(',').map(number).collect::<Result<_, _>>()?
+        };
+
+        if values.is_empty() {
+            return Err(format!("'{}' has no values", text));
+        }
+
+        Ok(Axis { parameter, values })
+    }
+}
+
+/// One point of the parameter grid
+#[derive(Clone, Debug)]
+pub struct Case {
+    /// Position of the case in the expanded grid
+    pub index: usize,
+    /// The value of every swept parameter, in axis order
+    pub values: Vec<(Parameter, f64)>,
+}
+
+/// The outcome of running one case
+#[derive(Clone, Debug)]
+pub struct CaseResult {
+    /// The case that was run
+    pub case: Case,
+    /// The summary of the run, or why it could not be run
+    pub outcome: Result<RunSummary, String>,
+}
+
+/// A scenario run once for every combination of parameter values
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    /// The scenario every case starts from
+    pub scenario: Scenario,
+    /// The parameters varied, each combination of their values being one case
+    pub axes: Vec<Axis>,
+    /// How each case is run
+    pub config: RunConfig,
+    /// Largest random displacement of each position coordinate in seeded cases
+    pub jitter: f64,
+}
+
+impl Sweep {
+    /// Expand the grid into the full list of cases, the last axis varying fastest
+    pub fn cases(&self) -> Vec<Case> {
+        let mut cases = vec![vec![]];
+        for axis in self.axes.iter() {
+            cases = cases
+                .into_iter()
+                .flat_map(|values: Vec<(Parameter, f64)>| {
+                    axis.values.iter().map(move |value| {
+                        let mut values = values.clone();
+                        values.push((axis.parameter, *value));
+                        values
+                    })
+                })
+                .collect();
+        }
+
+        cases
+            .into_iter()
+            .enumerate()
+            .map(|(index, values)| Case { index, values })
+            .collect()
+    }
+
+    /// The scenario with the parameter values of `case` applied
+    pub fn scenario_for(&self, case: &Case) -> Result<Scenario, String> {
+        let mut scenario = self.scenario.clone();
+        for (parameter, value) in case.values.iter() {
+            let value = *value;
+            let objects = &mut scenario.objects;
+            let selected = |index: &Option<usize>| -> Result<Vec<usize>, String> {
+                match index {
+                    Some(i) if *i < objects.len() => Ok(vec![*i]),
+                    Some(i) => Err(format!("{} refers to missing object {}", parameter, i)),
+                    None => Ok((0..objects.len()).collect()),
+                }
+            };
+
+            match parameter {
+                Parameter::Offset(index) => {
+                    for i in selected(index)? {
+                        if objects[i].kind == Objects::Dipole {
+                            objects[i].offset = value;
+                        }
+                    }
+                }
+                Parameter::Charge(index) => {
+                    for i in selected(index)? {
+                        let sign = if objects[i].charge < 0. { -1. } else { 1. };
+                        objects[i].charge = sign * value.abs();
+                    }
+                }
+                Parameter::Mass(index) => {
+                    for i in selected(index)? {
+                        objects[i].mass = value;
+                    }
+                }
+                // Without any jitter every seed gives the same case, and rand can't sample
+                // from an empty range
+                Parameter::Seed if self.jitter <= 0. => {}
+                Parameter::Seed => {
+                    let mut rng = StdRng::seed_from_u64(value as u64);
+                    for object in objects.iter_mut() {
+                        for x in object.position.iter_mut() {
+                            *x += rng.gen_range(-self.jitter, self.jitter);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(scenario)
+    }
+
+    /// Build and run a single case
+    pub fn run_case(&self, case: &Case) -> CaseResult {
+        let outcome = self.scenario_for(case).and_then(|scenario| {
+            let mut simulation = scenario.build()?;
+            batch::run(simulation.as_mut(), &self.config, |_, _, _| Ok(()))
+                .map_err(|err| err.to_string())
+        });
+
+        CaseResult {
+            case: case.clone(),
+            outcome,
+        }
+    }
+
+    /// Run every case on `threads` worker threads. The results are in case order.
+    pub fn run(&self, threads: usize) -> Vec<CaseResult> {
+        let cases = self.cases();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(cases.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1).min(cases.len().max(1)) {
+                scope.spawn(|| {
+                    while let Some(case) = cases.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let result = self.run_case(case);
+                        results.lock().unwrap().push(result);
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|result| result.case.index);
+        results
+    }
+
+    /// Write one CSV row per case: the parameter values followed by the final separation, the
+    /// final and largest relative energy drift and the time at which the objects collapsed.
+    ///
+    /// Cases that never collapsed leave the collapse time empty. Cases that could not be run
+    /// leave every outcome empty and give the reason in the last column.
+    pub fn write_csv<W: Write>(&self, results: &[CaseResult], out: &mut W) -> io::Result<()> {
+        write!(out, "case")?;
+        for axis in self.axes.iter() {
+            write!(out, ",{}", axis.parameter)?;
+        }
+        writeln!(
+            out,
+            ",final_separation,energy_drift,max_energy_drift,time_to_collapse,steps,time,error"
+        )?;
+
+        for result in results.iter() {
+            write!(out, "{}", result.case.index)?;
+            for (_, value) in result.case.values.iter() {
+                write!(out, ",{}", value)?;
+            }
+            match &result.outcome {
+                Ok(summary) => {
+                    let collapse = summary
+                        .collapsed_at
+                        .map(|time| time.to_string())
+                        .unwrap_or_default();
+                    writeln!(
+                        out,
+                        ",{},{},{},{},{},{},",
+                        summary.final_separation,
+                        summary.final_drift.energy.relative,
+                        summary.max_drift.energy.relative,
+                        collapse,
+                        summary.steps,
+                        summary.time
+                    )?;
+                }
+                Err(err) => writeln!(out, ",,,,,,,\"{}\"", err.replace('"', "'"))?,
+            }
+        }
+        Ok(())
+    }
+}
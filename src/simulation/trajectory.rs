@@ -0,0 +1,257 @@
+//! Recording runs and exporting them as text for other tools
+
+use crate::simulation::binary_trajectory::Header;
+use crate::simulation::binary_trajectory::TrajectoryWriter;
+use crate::simulation::dipole::rotate;
+use crate::simulation::dipole::Object;
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use crate::simulation::dipole::K;
+use nalgebra::Vector3;
+use std::io::{self, Seek, Write};
+
+/// Description of the unit system positions, masses and charges are expressed in
+pub fn units() -> String {
+    format!("reduced (coulomb constant K = {})", K)
+}
+
+/// The state of a single object at one instant of a recorded trajectory
+#[derive(Clone, Debug)]
+pub struct ObjectState {
+    /// What kind of object this is
+    pub kind: Objects,
+    /// Total mass
+    pub mass: f64,
+    /// The charge of a point charge, or the magnitude of each charge of a dipole
+    pub charge: f64,
+    /// Distance from the centre to each charge of a dipole
+    pub offset: f64,
+    /// Position of the centre of mass
+    pub position: Vector3<f64>,
+    /// Unit vector from the negative to the positive charge of a dipole
+    pub orientation: Vector3<f64>,
+    /// Velocity of the centre of mass
+    pub velocity: Vector3<f64>,
+    /// Angular velocity about the centre of mass
+    pub angular_velocity: Vector3<f64>,
+}
+
+impl Object for ObjectState {
+    fn update(
+        &mut self,
+        d_pos: Vector3<f64>,
+        d_vel: Vector3<f64>,
+        d_orient: Vector3<f64>,
+        d_ang_vel: Vector3<f64>,
+    ) {
+        self.position += d_pos;
+        self.velocity += d_vel;
+        self.orientation = rotate(self.orientation, d_orient);
+        self.angular_velocity += d_ang_vel;
+    }
+    fn get_type(&self) -> Objects {
+        self.kind
+    }
+    fn get_pos(&self) -> Vector3<f64> {
+        self.position
+    }
+    fn get_orientation(&self) -> Vector3<f64> {
+        self.orientation
+    }
+    fn get_offset(&self) -> f64 {
+        self.offset
+    }
+    fn get_velocity(&self) -> Vector3<f64> {
+        self.velocity
+    }
+    fn get_angular_velocity(&self) -> Vector3<f64> {
+        self.angular_velocity
+    }
+    fn get_mass(&self) -> f64 {
+        self.mass
+    }
+    fn get_charge(&self) -> f64 {
+        self.charge
+    }
+}
+
+/// Every object of a simulation at one point in simulated time
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Simulated time of the frame
+    pub time: f64,
+    /// The state of every object, in simulation order
+    pub objects: Vec<ObjectState>,
+}
+
+impl Frame {
+    /// Snapshot the current state of every object in the simulation
+    pub fn capture(simulation: &dyn Simulatable, time: f64) -> Frame {
+        let objects = simulation
+            .get_objects()
+            .iter()
+            .map(|object| ObjectState {
+                kind: object.get_type(),
+                mass: object.get_mass(),
+                charge: object.get_charge(),
+                offset: object.get_offset(),
+                position: object.get_pos(),
+                orientation: object.get_orientation(),
+                velocity: object.get_velocity(),
+                angular_velocity: object.get_angular_velocity(),
+            })
+            .collect();
+
+        Frame { time, objects }
+    }
+
+    /// Write the frame in the extended XYZ format.
+    ///
+    /// The frame is an atom count line, a comment line describing the per-object columns and
+    /// the simulated time, then one line per object.
+    pub fn write_extended_xyz<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", self.objects.len())?;
+        writeln!(
+            out,
+            "Properties=species:S:1:pos:R:3:orientation:R:3:velo:R:3:angular_velocity:R:3:\
+             mass:R:1:charge:R:1:offset:R:1 Time={}",
+            self.time
+        )?;
+        for object in self.objects.iter() {
+            let species = match object.kind {
+                Objects::Dipole => "D",
+                Objects::Charge => "Q",
+            };
+            write!(out, "{}", species)?;
+            for v in [
+                object.position,
+                object.orientation,
+                object.velocity,
+                object.angular_velocity,
+            ]
+            .iter()
+            {
+                write!(out, " {} {} {}", v.x, v.y, v.z)?;
+            }
+            writeln!(out, " {} {} {}", object.mass, object.charge, object.offset)?;
+        }
+        Ok(())
+    }
+
+    /// Write one CSV row per object, without the header
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (index, object) in self.objects.iter().enumerate() {
+            let kind = match object.kind {
+                Objects::Dipole => "dipole",
+                Objects::Charge => "charge",
+            };
+            write!(out, "{},{},{}", self.time, index, kind)?;
+            for v in [
+                object.position,
+                object.orientation,
+                object.velocity,
+                object.angular_velocity,
+            ]
+            .iter()
+            {
+                write!(out, ",{},{},{}", v.x, v.y, v.z)?;
+            }
+            writeln!(out, ",{},{},{}", object.mass, object.charge, object.offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records frames of a running simulation so that they can be exported for post-processing
+/// in tools such as OVITO or VMD.
+///
+/// Only every `stride`th step is kept, which slows the growth of long runs without bounding it:
+/// frames are kept until the trajectory is cleared.
+pub struct Trajectory {
+    stride: usize,
+    steps: usize,
+    frames: Vec<Frame>,
+}
+
+impl Trajectory {
+    /// An empty trajectory keeping every `stride`th step
+    pub fn new(stride: usize) -> Trajectory {
+        Trajectory {
+            stride: stride.max(1),
+            steps: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// How many steps pass between recorded frames
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Change how many steps pass between recorded frames. A stride of 0 is treated as 1.
+    pub fn set_stride(&mut self, stride: usize) {
+        self.stride = stride.max(1);
+    }
+
+    /// Every recorded frame, oldest first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Forget every recorded frame
+    pub fn clear(&mut self) {
+        self.steps = 0;
+        self.frames.clear();
+    }
+
+    /// Call once per simulation step. A frame is captured on every `stride`th call.
+    pub fn record(&mut self, simulation: &dyn Simulatable, time: f64) {
+        if self.steps.is_multiple_of(self.stride) {
+            self.frames.push(Frame::capture(simulation, time));
+        }
+        self.steps += 1;
+    }
+
+    /// Write every recorded frame in the extended XYZ format
+    pub fn write_extended_xyz<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for frame in self.frames.iter() {
+            frame.write_extended_xyz(out)?;
+        }
+        Ok(())
+    }
+
+    /// Write every recorded frame in the binary trajectory format
+    pub fn write_binary<W: Write + Seek>(&self, out: W, integrator: &str) -> io::Result<W> {
+        let header = match self.frames.first() {
+            Some(frame) => Header::from_frame(frame, &units(), integrator),
+            None => Header {
+                units: units(),
+                integrator: integrator.to_string(),
+                objects: vec![],
+            },
+        };
+
+        let mut writer = TrajectoryWriter::new(out, header)?;
+        for frame in self.frames.iter() {
+            writer.write_frame(frame)?;
+        }
+        writer.finish()
+    }
+
+    /// Write every recorded frame as CSV with one row per object per frame
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write_csv_header(out)?;
+        for frame in self.frames.iter() {
+            frame.write_csv(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the column names matching `Frame::write_csv`
+pub fn write_csv_header<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(
+        out,
+        "time,index,type,x,y,z,ox,oy,oz,vx,vy,vz,wx,wy,wz,mass,charge,offset"
+    )
+}
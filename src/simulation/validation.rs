@@ -0,0 +1,329 @@
+//! Reference scenarios with closed-form answers, used to measure the error of each integrator.
+//!
+//! Each reference is a small scenario whose behaviour is known exactly. Running it with a given
+//! integrator and time step and comparing one measured quantity against the analytic value shows
+//! how far the numerical solution can be trusted.
+
+use crate::simulation::dipole::Objects;
+use crate::simulation::dipole::Simulatable;
+use crate::simulation::dipole::K;
+use crate::simulation::integrator::Integrator;
+use crate::simulation::scenario::{ObjectSpec, Scenario};
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Masses of the two orbiting charges. They differ so that the centre of mass motion is tested.
+const ORBIT_MASSES: (f64, f64) = (1., 2.);
+/// Semi-major axis of the relative orbit
+const ORBIT_SEMI_MAJOR_AXIS: f64 = 2.;
+/// Mass, charge and offset of the librating dipole
+const LIBRATION_DIPOLE: (f64, f64, f64) = (1., 1., 0.1);
+/// Strength of the uniform field the dipole librates in
+const LIBRATION_FIELD: f64 = 1.;
+/// Initial separation of the colliding charges
+const COLLISION_SEPARATION: f64 = 10.;
+
+/// A scenario with a closed-form result
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reference {
+    /// Two opposite unit charges on a Kepler orbit about their centre of mass, starting at
+    /// periapsis. The measured quantity is the orbital period, `2π √(μ a³ / k)` for reduced mass
+    /// `μ`, semi-major axis `a` and coupling `k = K q²`.
+    Orbit {
+        /// Zero for a circular orbit
+        eccentricity: f64,
+    },
+    /// A dipole released at rest at an angle to a uniform field, swinging about the field like a
+    /// pendulum. The measured quantity is the period, which for small amplitudes is
+    /// `2π √(I / (p E))`. The exact pendulum period is used so that only the integrator
+    /// contributes to the error.
+    Libration {
+        /// Initial angle between the dipole and the field, in radians
+        amplitude: f64,
+    },
+    /// Two like unit charges fired head on at each other. The measured quantity is the distance
+    /// of closest approach, where all of the kinetic energy in the centre of mass frame has been
+    /// turned into potential energy.
+    HeadOnCollision {
+        /// Initial speed of each charge
+        speed: f64,
+    },
+}
+
+impl Reference {
+    /// A circular and an elliptic orbit, a small libration and a head-on collision
+    pub fn standard() -> Vec<Reference> {
+        vec![
+            Reference::Orbit { eccentricity: 0. },
+            Reference::Orbit { eccentricity: 0.5 },
+            Reference::Libration { amplitude: 0.1 },
+            Reference::HeadOnCollision { speed: 1. },
+        ]
+    }
+
+    /// What is measured
+    pub fn quantity(&self) -> &'static str {
+        match self {
+            Reference::Orbit { .. } => "orbital period",
+            Reference::Libration { .. } => "libration period",
+            Reference::HeadOnCollision { .. } => "closest approach",
+        }
+    }
+
+    /// The initial conditions
+    pub fn scenario(&self) -> Scenario {
+        match *self {
+            Reference::Orbit { eccentricity } => {
+                let (m1, m2) = ORBIT_MASSES;
+                let total = m1 + m2;
+                let reduced = m1 * m2 / total;
+                let coupling = K;
+                let periapsis = ORBIT_SEMI_MAJOR_AXIS * (1. - eccentricity);
+                // Vis-viva at periapsis
+                let speed = (coupling / reduced * (1. + eccentricity) / periapsis).sqrt();
+
+                Scenario {
+                    name: format!("Kepler orbit, eccentricity {}", eccentricity),
+                    objects: vec![
+                        charge(m1, 1., -m2 / total * periapsis, -m2 / total * speed),
+                        charge(m2, -1., m1 / total * periapsis, m1 / total * speed),
+                    ],
+                    external_field: [0.; 3],
+                }
+            }
+            Reference::Libration { amplitude } => {
+                let (mass, charge, offset) = LIBRATION_DIPOLE;
+                Scenario {
+                    name: format!("dipole libration, amplitude {}", amplitude),
+                    objects: vec![ObjectSpec {
+                        kind: Objects::Dipole,
+                        mass,
+                        charge,
+                        offset,
+                        position: [0.; 3],
+                        velocity: [0.; 3],
+                        orientation: [amplitude.cos(), amplitude.sin(), 0.],
+                        angular_velocity: [0.; 3],
+                    }],
+                    external_field: [LIBRATION_FIELD, 0., 0.],
+                }
+            }
+            Reference::HeadOnCollision { speed } => {
+                let half = COLLISION_SEPARATION / 2.;
+                let mut left = charge(1., 1., -half, 0.);
+                let mut right = charge(1., 1., half, 0.);
+                left.velocity = [speed, 0., 0.];
+                right.velocity = [-speed, 0., 0.];
+
+                Scenario {
+                    name: format!("head-on collision, speed {}", speed),
+                    objects: vec![left, right],
+                    external_field: [0.; 3],
+                }
+            }
+        }
+    }
+
+    /// The analytic value of the measured quantity
+    pub fn expected(&self) -> f64 {
+        match *self {
+            Reference::Orbit { .. } => {
+                let (m1, m2) = ORBIT_MASSES;
+                let reduced = m1 * m2 / (m1 + m2);
+                2. * PI * (reduced * ORBIT_SEMI_MAJOR_AXIS.powi(3) / K).sqrt()
+            }
+            Reference::Libration { amplitude } => {
+                let (mass, charge, offset) = LIBRATION_DIPOLE;
+                let moment = mass * offset * offset;
+                let dipole_moment = 2. * charge * offset;
+                let small_angle = (moment / (dipole_moment * LIBRATION_FIELD)).sqrt();
+                // Exact pendulum period, 4 √(I / (p E)) K(sin(θ / 2))
+                4. * small_angle * elliptic_k((amplitude / 2.).sin())
+            }
+            Reference::HeadOnCollision { speed } => {
+                let reduced = 0.5;
+                let relative_speed = 2. * speed;
+                K / (K / COLLISION_SEPARATION + reduced * relative_speed * relative_speed / 2.)
+            }
+        }
+    }
+
+    /// Run the scenario with `integrator` and steps of `dt`, and compare the measured quantity
+    /// against the analytic value.
+    pub fn validate(&self, integrator: Integrator, dt: f64) -> Validation {
+        let mut simulation = self
+            .scenario()
+            .build()
+            .expect("reference scenarios are valid");
+        simulation.set_integrator(integrator);
+        let expected = self.expected();
+
+        let measured = match self {
+            Reference::Orbit { .. } => measure_orbit_period(simulation.as_mut(), dt, expected),
+            Reference::Libration { .. } => {
+                measure_libration_period(simulation.as_mut(), dt, expected)
+            }
+            Reference::HeadOnCollision { .. } => measure_closest_approach(simulation.as_mut(), dt),
+        };
+
+        Validation {
+            reference: *self,
+            integrator,
+            dt,
+            expected,
+            measured,
+        }
+    }
+}
+
+/// Validate every standard reference with one integrator
+pub fn validate_all(integrator: Integrator, dt: f64) -> Vec<Validation> {
+    Reference::standard()
+        .iter()
+        .map(|reference| reference.validate(integrator, dt))
+        .collect()
+}
+
+/// How closely a run reproduced a reference
+#[derive(Clone, Debug)]
+pub struct Validation {
+    /// The reference that was run
+    pub reference: Reference,
+    /// The integrator used
+    pub integrator: Integrator,
+    /// The time step used
+    pub dt: f64,
+    /// The analytic value
+    pub expected: f64,
+    /// The value measured from the run. NaN if it could not be measured, for example because
+    /// the run blew up.
+    pub measured: f64,
+}
+
+impl Validation {
+    /// `|measured - expected| / |expected|`
+    pub fn relative_error(&self) -> f64 {
+        ((self.measured - self.expected) / self.expected).abs()
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) with {} at dt {}: expected {:.9}, measured {:.9}, relative error {:.3e}",
+            self.reference.scenario().name,
+            self.reference.quantity(),
+            self.integrator,
+            self.dt,
+            self.expected,
+            self.measured,
+            self.relative_error()
+        )
+    }
+}
+
+fn charge(mass: f64, charge: f64, x: f64, vy: f64) -> ObjectSpec {
+    ObjectSpec {
+        kind: Objects::Charge,
+        mass,
+        charge,
+        offset: 0.1,
+        position: [x, 0., 0.],
+        velocity: [0., vy, 0.],
+        orientation: [1., 0., 0.],
+        angular_velocity: [0.; 3],
+    }
+}
+
+/// Complete elliptic integral of the first kind with modulus `k`, by the arithmetic-geometric mean
+fn elliptic_k(k: f64) -> f64 {
+    let (mut a, mut b) = (1., (1. - k * k).sqrt());
+    while (a - b).abs() > 1e-15 * a {
+        let next = ((a + b) / 2., (a * b).sqrt());
+        a = next.0;
+        b = next.1;
+    }
+    PI / (2. * a)
+}
+
+/// Time at which the straight line through `(t0, y0)` and `(t1, y1)` reaches `y`
+fn interpolate(t0: f64, y0: f64, t1: f64, y1: f64, y: f64) -> f64 {
+    t0 + (t1 - t0) * (y - y0) / (y1 - y0)
+}
+
+/// Time for the separation vector of the two charges to sweep through a full turn
+fn measure_orbit_period(simulation: &mut dyn Simulatable, dt: f64, expected: f64) -> f64 {
+    let separation = |simulation: &dyn Simulatable| {
+        let objects = simulation.get_objects();
+        objects[1].get_pos() - objects[0].get_pos()
+    };
+
+    let mut time = 0.;
+    let mut previous = separation(simulation);
+    let mut swept = 0.;
+    while time < 1.5 * expected {
+        simulation.update(dt);
+        let current = separation(simulation);
+        let step = previous.x * current.y - previous.y * current.x;
+        let angle = step.atan2(previous.dot(&current));
+        if swept + angle >= 2. * PI {
+            return interpolate(time, swept, time + dt, swept + angle, 2. * PI);
+        }
+        swept += angle;
+        previous = current;
+        time += dt;
+    }
+    f64::NAN
+}
+
+/// Time between the first and third crossings of the field direction
+fn measure_libration_period(simulation: &mut dyn Simulatable, dt: f64, expected: f64) -> f64 {
+    let angle = |simulation: &dyn Simulatable| {
+        let orientation = simulation.get_objects()[0].get_orientation();
+        orientation.y.atan2(orientation.x)
+    };
+
+    let mut time = 0.;
+    let mut previous = angle(simulation);
+    let mut crossings = vec![];
+    while time < 2. * expected {
+        simulation.update(dt);
+        let current = angle(simulation);
+        if previous.signum() != current.signum() {
+            crossings.push(interpolate(time, previous, time + dt, current, 0.));
+            if crossings.len() == 3 {
+                return crossings[2] - crossings[0];
+            }
+        }
+        previous = current;
+        time += dt;
+    }
+    f64::NAN
+}
+
+/// Smallest separation of the two charges, refined by fitting a parabola through the samples
+/// around the minimum
+fn measure_closest_approach(simulation: &mut dyn Simulatable, dt: f64) -> f64 {
+    let separation = |simulation: &dyn Simulatable| {
+        let objects = simulation.get_objects();
+        (objects[1].get_pos() - objects[0].get_pos()).norm()
+    };
+
+    let mut samples = [f64::INFINITY, f64::INFINITY, separation(simulation)];
+    let mut time = 0.;
+    while time < 100. {
+        simulation.update(dt);
+        samples = [samples[1], samples[2], separation(simulation)];
+        if samples[2] > samples[1] {
+            let [a, b, c] = samples;
+            let curvature = a - 2. * b + c;
+            if !a.is_finite() || curvature <= 0. {
+                return b;
+            }
+            return b - (a - c) * (a - c) / (8. * curvature);
+        }
+        time += dt;
+    }
+    f64::NAN
+}
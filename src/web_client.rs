@@ -0,0 +1,566 @@
+use crate::app::*;
+use crate::canvas::*;
+use crate::charts::*;
+use crate::controls::*;
+use crate::inspector::*;
+use crate::labels::*;
+use crate::logging;
+use crate::render::*;
+use crate::simulation::comparison::Settings;
+use crate::simulation::dipole::Object;
+use crate::simulation::scenario::{ObjectSpec, Scenario};
+use js_sys::{Float64Array, Function, Object as JsObject, Reflect};
+use nalgebra::Vector3;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::*;
+
+/// Used to run the application from the web
+#[wasm_bindgen]
+pub struct WebClient {
+    app: Rc<App>,
+    gl: Rc<WebGlRenderingContext>,
+    stage: HtmlElement,
+    canvas: HtmlCanvasElement,
+    renderer: WebRenderer,
+    chart: Chart,
+    readouts: Readouts,
+    inspector: Inspector,
+    labels: Labels,
+    listeners: RefCell<Vec<(&'static str, Function)>>,
+}
+#[wasm_bindgen]
+impl WebClient {
+    /// Create a new web client inside `mount`, or inside the element with id
+    /// `dipole-simulation` when no element is given. Each client keeps to its own elements, so
+    /// several can run side by side on one page.
+    #[wasm_bindgen(constructor)]
+    pub fn new(mount: Option<HtmlElement>) -> WebClient {
+        console_error_panic_hook::set_once();
+
+        let app = Rc::new(App::new());
+
+        let root = create_root(mount).expect("Root element");
+        let stage = create_stage(&root).expect("Stage");
+        let canvas = create_canvas(Rc::clone(&app), &stage).expect("Canvas");
+        let gl = Rc::new(create_webgl_context(&canvas).unwrap());
+        let labels = Labels::new(&stage).expect("Labels");
+        let chart = Chart::new(&root).expect("Chart");
+        let inspector = Inspector::new(Rc::clone(&app), &root).expect("Inspector");
+        let readouts = append_controls(Rc::clone(&app), &root).expect("Append controls");
+
+        let renderer = WebRenderer::new(&gl);
+
+        WebClient {
+            app,
+            gl,
+            stage,
+            canvas,
+            renderer,
+            chart,
+            readouts,
+            inspector,
+            labels,
+            listeners: RefCell::new(vec![]),
+        }
+    }
+
+    /// Start our WebGL Water application. `index.html` will call this function in order
+    /// to begin rendering.
+    pub fn start(&self) -> Result<(), JsValue> {
+        Ok(())
+    }
+
+    /// Update our simulation
+    pub fn update(&self, dt: f32) {
+        self.app.store.borrow_mut().msg(&Msg::AdvanceClock(dt));
+        self.msg(&Msg::UpdateSimulation(dt));
+        self.dispatch_events();
+
+        update_values(&self.readouts, &self.app);
+        self.inspector.update(&self.app);
+
+        // web_sys::console::log_1(&format!("L: {:?}, P: {:?}, E: {:?}", l, p, e).into());
+    }
+
+    /// Render the scene. `index.html` will call this once every requestAnimationFrame
+    pub fn render(&mut self) {
+        // The page or the screen may have changed the size the canvas is shown at
+        fit_canvas(&self.canvas, &self.app);
+        self.renderer
+            .render(&self.gl, &self.app.store.borrow().state, self.app.assets());
+        self.chart.draw(&self.app.store.borrow().state);
+        self.labels.draw(&self.app.store.borrow().state);
+    }
+
+    /// Call `callback` with an object describing each event of a kind:
+    ///
+    /// - `"step"`: `{ time, energy, kinetic, potential, momentum, angularMomentum, temperature }`
+    ///   after every step
+    /// - `"closeapproach"`: `{ time, first, second, distance }` when two objects come closer
+    ///   than the close approach distance
+    /// - `"driftexceeded"`: `{ time, energy, momentum, angularMomentum }` with the relative drift
+    ///   of each, the first time one exceeds the drift threshold after a reset
+    /// - `"reset"`: `{}` when the simulation is reset or replaced
+    ///
+    /// Events are delivered in the order they happened, once a frame from `update`.
+    pub fn on(&self, event: &str, callback: Function) -> Result<(), JsValue> {
+        let name = SimulationEvent::NAMES
+            .iter()
+            .find(|name| **name == event)
+            .ok_or_else(|| format!("unknown event '{}'", event))?;
+        self.listeners.borrow_mut().push((name, callback));
+        Ok(())
+    }
+
+    /// Stop calling a callback registered with `on`
+    pub fn off(&self, event: &str, callback: &Function) {
+        self.listeners
+            .borrow_mut()
+            .retain(|(name, listener)| !(*name == event && listener == callback));
+    }
+
+    /// Relative drift of a conserved quantity above which `"driftexceeded"` is raised
+    #[wasm_bindgen(js_name = setDriftThreshold)]
+    pub fn set_drift_threshold(&self, threshold: f64) {
+        self.msg(&Msg::DriftThreshold(threshold));
+    }
+
+    /// Distance between two objects below which `"closeapproach"` is raised
+    #[wasm_bindgen(js_name = setCloseApproachDistance)]
+    pub fn set_close_approach_distance(&self, distance: f64) {
+        self.msg(&Msg::CloseApproachDistance(distance));
+    }
+
+    /// Replace the simulation with one starting from a scenario in JSON, in the format written
+    /// by `saveScenario`
+    #[wasm_bindgen(js_name = loadScenario)]
+    pub fn load_scenario(&self, json: &str) -> Result<(), JsValue> {
+        self.load(Scenario::from_json(json)?)
+    }
+
+    /// Stop playing a recording and go back to the simulation it replaced, from its start
+    #[wasm_bindgen(js_name = leavePlayback)]
+    pub fn leave_playback(&self) {
+        self.msg(&Msg::LeavePlayback);
+    }
+
+    /// The current state of the simulation as scenario JSON
+    #[wasm_bindgen(js_name = saveScenario)]
+    pub fn save_scenario(&self) -> String {
+        self.scenario().to_json()
+    }
+
+    /// Add an object, given as JSON in the format of a scenario entry, select it and return its
+    /// index. The object resets to the state it is added in. Fails with the reason if the
+    /// simulation won't take the object, such as a point charge among dipoles.
+    #[wasm_bindgen(js_name = addObject)]
+    pub fn add_object(&self, json: &str) -> Result<usize, JsValue> {
+        let object = ObjectSpec::from_json(json)?;
+        object.check()?;
+        let count = self.object_count();
+        self.msg(&Msg::AddObject(object));
+        self.added(count)
+    }
+
+    /// Add a copy of the object at `index` beside it, select it and return its index
+    #[wasm_bindgen(js_name = duplicateObject)]
+    pub fn duplicate_object(&self, index: usize) -> Result<usize, JsValue> {
+        let count = self.object_count();
+        if index >= count {
+            return Err(format!("there is no object {}", index).into());
+        }
+        self.msg(&Msg::DuplicateObject(index));
+        self.added(count)
+    }
+
+    /// Remove the object at `index`. Later objects move down one place.
+    #[wasm_bindgen(js_name = removeObject)]
+    pub fn remove_object(&self, index: usize) -> Result<(), JsValue> {
+        let count = self.object_count();
+        if index >= count {
+            return Err(format!("there is no object {}", index).into());
+        }
+        self.msg(&Msg::DeleteObject(index));
+        if self.object_count() == count {
+            let err = if self.app.store.borrow().state.playing_back() {
+                "can't remove objects from a recording".to_string()
+            } else {
+                format!("could not remove object {}", index)
+            };
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Number of objects being simulated
+    #[wasm_bindgen(js_name = objectCount)]
+    pub fn object_count(&self) -> usize {
+        self.app
+            .store
+            .borrow()
+            .state
+            .simulation()
+            .get_objects()
+            .len()
+    }
+
+    /// Distance from the centre to each charge of every dipole
+    #[wasm_bindgen(js_name = setOffset)]
+    pub fn set_offset(&self, offset: f32) {
+        self.msg(&Msg::Offset(offset));
+    }
+
+    /// Seconds of simulated time per second of real time
+    #[wasm_bindgen(js_name = setTimeScale)]
+    pub fn set_time_scale(&self, time_scale: f32) {
+        self.msg(&Msg::TimeScale(time_scale));
+    }
+
+    /// Step with "euler", "verlet" or "rk4"
+    #[wasm_bindgen(js_name = setIntegrator)]
+    pub fn set_integrator(&self, name: &str) -> Result<(), JsValue> {
+        let integrator = name.parse()?;
+        self.app
+            .store
+            .borrow_mut()
+            .msg(&Msg::Integrator(integrator));
+        Ok(())
+    }
+
+    /// Apply a uniform field on top of the field of the objects
+    #[wasm_bindgen(js_name = setExternalField)]
+    pub fn set_external_field(&self, x: f64, y: f64, z: f64) {
+        self.msg(&Msg::ExternalField(Vector3::new(x, y, z)));
+    }
+
+    /// Hold the simulation still. Rendering and the camera keep working.
+    pub fn pause(&self) {
+        self.msg(&Msg::Paused(true));
+    }
+
+    /// Let a paused simulation run again
+    pub fn resume(&self) {
+        self.msg(&Msg::Paused(false));
+    }
+
+    /// Advance by a single physics step. Works while paused, to step through a run.
+    pub fn step(&self) {
+        self.msg(&Msg::Step);
+    }
+
+    /// Seconds of simulated time per physics step. Frames advance the simulation by as many
+    /// whole steps as fit in the time since the last one, scaled by the time scale.
+    #[wasm_bindgen(js_name = setPhysicsDt)]
+    pub fn set_physics_dt(&self, dt: f64) {
+        self.msg(&Msg::PhysicsDt(dt));
+    }
+
+    /// Whether the simulation is paused
+    #[wasm_bindgen(js_name = isPaused)]
+    pub fn is_paused(&self) -> bool {
+        self.app.store.borrow().state.paused()
+    }
+
+    /// Return the simulation to its initial state
+    pub fn reset(&self) {
+        self.msg(&Msg::ResetSimulation);
+    }
+
+    /// Simulated time since the last reset
+    #[wasm_bindgen(js_name = simTime)]
+    pub fn sim_time(&self) -> f64 {
+        self.app.store.borrow().state.sim_time()
+    }
+
+    /// Run the scenario again from the start alongside the simulation, in lock-step, with other
+    /// settings given as JSON: `{ "integrator": "euler", "substeps": 4, "offset": 0.2, "model":
+    /// "point" }`. The model is `"finite"`, for dipoles made of two charges, or `"point"`, for
+    /// ideal point dipoles. Missing settings keep their defaults. Without settings the comparison
+    /// stops.
+    pub fn compare(&self, settings: Option<String>) -> Result<(), JsValue> {
+        let settings = match settings {
+            Some(json) => Some(Settings::from_json(&json)?),
+            None => None,
+        };
+        self.msg(&Msg::Compare(settings));
+        Ok(())
+    }
+
+    /// Draw the comparison in its own half of the canvas with `"split"`, or faded over the
+    /// simulation with `"ghost"`
+    #[wasm_bindgen(js_name = setCompareView)]
+    pub fn set_compare_view(&self, view: &str) -> Result<(), JsValue> {
+        let view = match view {
+            "split" => CompareView::Split,
+            "ghost" => CompareView::Ghost,
+            other => return Err(format!("unknown view '{}'", other).into()),
+        };
+        self.msg(&Msg::CompareView(view));
+        Ok(())
+    }
+
+    /// Look at and orbit about the point `x`, `y`, `z`, and stop following anything
+    #[wasm_bindgen(js_name = setCameraTarget)]
+    pub fn set_camera_target(&self, x: f64, y: f64, z: f64) {
+        self.msg(&Msg::CameraTarget(Vector3::new(x, y, z)));
+    }
+
+    /// Keep `"center-of-mass"` or the `"selected"` object in the centre of the view as the
+    /// simulation runs, or nothing with `"free"`. Panning by hand goes back to `"free"`.
+    #[wasm_bindgen(js_name = setFollow)]
+    pub fn set_follow(&self, follow: &str) -> Result<(), JsValue> {
+        let follow = match follow {
+            "free" => Follow::Free,
+            "center-of-mass" => Follow::CenterOfMass,
+            "selected" => Follow::Selected,
+            other => return Err(format!("unknown follow mode '{}'", other).into()),
+        };
+        self.msg(&Msg::Follow(follow));
+        Ok(())
+    }
+
+    /// Swing the camera round to look from `"top"`, `"front"`, `"side"` or `"isometric"`
+    #[wasm_bindgen(js_name = lookFrom)]
+    pub fn look_from(&self, preset: &str) -> Result<(), JsValue> {
+        let preset = Preset::ALL
+            .iter()
+            .find(|known| known.name() == preset)
+            .ok_or_else(|| format!("unknown view '{}'", preset))?;
+        self.msg(&Msg::LookFrom(*preset));
+        Ok(())
+    }
+
+    /// Draw the view in `"perspective"`, or flat with `"orthographic"`
+    #[wasm_bindgen(js_name = setProjection)]
+    pub fn set_projection(&self, projection: &str) -> Result<(), JsValue> {
+        let projection = match projection {
+            "perspective" => Projection::Perspective,
+            "orthographic" => Projection::Orthographic,
+            other => return Err(format!("unknown projection '{}'", other).into()),
+        };
+        self.msg(&Msg::Projection(projection));
+        Ok(())
+    }
+
+    /// Show the canvas on the whole screen, or go back to the page if it already is. Browsers only
+    /// allow this in answer to something the user did, such as a click.
+    #[wasm_bindgen(js_name = toggleFullscreen)]
+    pub fn toggle_fullscreen(&self) -> Result<(), JsValue> {
+        toggle_fullscreen(&self.stage)
+    }
+
+    /// Move the camera to fit everything in the view
+    #[wasm_bindgen(js_name = frameAll)]
+    pub fn frame_all(&self) {
+        self.msg(&Msg::FrameAll);
+    }
+
+    /// Move the camera to fit the selected object in the view
+    #[wasm_bindgen(js_name = frameSelection)]
+    pub fn frame_selection(&self) {
+        self.msg(&Msg::FrameSelection);
+    }
+
+    /// `{ position, maxPosition, orientation, energy }` describing how far the comparison has
+    /// drifted from the simulation, or `undefined` when not comparing
+    pub fn divergence(&self) -> Option<JsObject> {
+        let divergence = self.app.store.borrow().state.divergence()?;
+        let object = JsObject::new();
+        let values = [
+            ("position", divergence.position),
+            ("maxPosition", divergence.max_position),
+            ("orientation", divergence.orientation),
+            ("energy", divergence.energy),
+        ];
+        for (key, value) in values.iter() {
+            Reflect::set(&object, &(*key).into(), &(*value).into())
+                .expect("setting a property of a plain object");
+        }
+        Some(object)
+    }
+
+    /// Select the object with this index, as if it was clicked, or nothing without an index
+    pub fn select(&self, index: Option<usize>) {
+        self.msg(&Msg::Select(index));
+    }
+
+    /// The index of the selected object, if any
+    pub fn selected(&self) -> Option<usize> {
+        self.app.store.borrow().state.selected()
+    }
+
+    /// Mass of every object
+    pub fn masses(&self) -> Vec<f64> {
+        self.scalars(|object| object.get_mass())
+    }
+
+    /// Charge of every object
+    pub fn charges(&self) -> Vec<f64> {
+        self.scalars(|object| object.get_charge())
+    }
+
+    /// Position of every object, as consecutive x, y, z triples
+    pub fn positions(&self) -> Vec<f64> {
+        self.vectors(|object| object.get_pos())
+    }
+
+    /// Orientation of every object, as consecutive x, y, z triples. Zero for point charges.
+    pub fn orientations(&self) -> Vec<f64> {
+        self.vectors(|object| object.get_orientation())
+    }
+
+    /// Velocity of every object, as consecutive x, y, z triples
+    pub fn velocities(&self) -> Vec<f64> {
+        self.vectors(|object| object.get_velocity())
+    }
+
+    /// Angular velocity of every object, as consecutive x, y, z triples
+    #[wasm_bindgen(js_name = angularVelocities)]
+    pub fn angular_velocities(&self) -> Vec<f64> {
+        self.vectors(|object| object.get_angular_velocity())
+    }
+
+    /// The electric field at a point, as an x, y, z triple
+    #[wasm_bindgen(js_name = fieldAt)]
+    pub fn field_at(&self, x: f64, y: f64, z: f64) -> Vec<f64> {
+        let field = self
+            .app
+            .store
+            .borrow()
+            .state
+            .simulation()
+            .get_field(Vector3::new(x, y, z));
+        vec![field.x, field.y, field.z]
+    }
+}
+
+impl WebClient {
+    /// Apply a message. The listeners hear of the events it raises with the next update.
+    fn msg(&self, msg: &Msg) {
+        self.app.store.borrow_mut().msg(msg);
+    }
+
+    /// Tell the listeners about the events raised since the last call, by whatever sent the
+    /// messages: these methods, the controls or the canvas
+    fn dispatch_events(&self) {
+        let events = self.app.store.borrow_mut().take_events();
+        for event in events {
+            // Cloned so that callbacks can subscribe and unsubscribe
+            let listeners: Vec<_> = self
+                .listeners
+                .borrow()
+                .iter()
+                .filter(|(name, _)| *name == event.name())
+                .map(|(_, callback)| callback.clone())
+                .collect();
+            if listeners.is_empty() {
+                continue;
+            }
+
+            let value = event_to_js(&event);
+            for callback in listeners {
+                if let Err(err) = callback.call1(&JsValue::NULL, &value) {
+                    logging::warn(&format!("{} callback failed: {:?}", event.name(), err));
+                }
+            }
+        }
+    }
+
+    /// The index of the object just added when there were `count` before, or why it wasn't
+    fn added(&self, count: usize) -> Result<usize, JsValue> {
+        if self.object_count() == count {
+            let store = self.app.store.borrow();
+            let err = store
+                .state
+                .add_error()
+                .unwrap_or("could not add the object");
+            return Err(err.into());
+        }
+        Ok(count)
+    }
+
+    fn scenario(&self) -> Scenario {
+        Scenario::capture(self.app.store.borrow().state.simulation(), "")
+    }
+
+    fn load(&self, scenario: Scenario) -> Result<(), JsValue> {
+        // Checked here as well so that a bad scenario is reported to the caller
+        scenario.build()?;
+        self.app
+            .store
+            .borrow_mut()
+            .msg(&Msg::LoadScenario(scenario));
+        Ok(())
+    }
+
+    fn scalars(&self, value: impl Fn(&dyn Object) -> f64) -> Vec<f64> {
+        let store = self.app.store.borrow();
+        store
+            .state
+            .simulation()
+            .get_objects()
+            .into_iter()
+            .map(value)
+            .collect()
+    }
+
+    fn vectors(&self, value: impl Fn(&dyn Object) -> Vector3<f64>) -> Vec<f64> {
+        let store = self.app.store.borrow();
+        store
+            .state
+            .simulation()
+            .get_objects()
+            .into_iter()
+            .flat_map(|object| value(object).iter().copied().collect::<Vec<_>>())
+            .collect()
+    }
+}
+
+impl Default for WebClient {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// The object passed to event callbacks
+fn event_to_js(event: &SimulationEvent) -> JsValue {
+    let object = JsObject::new();
+    let set = |key: &str, value: JsValue| {
+        Reflect::set(&object, &key.into(), &value).expect("setting a property of a plain object");
+    };
+    let vector = |v: Vector3<f64>| JsValue::from(Float64Array::from(&[v.x, v.y, v.z][..]));
+
+    match event {
+        SimulationEvent::Step { time, observables } => {
+            set("time", (*time).into());
+            set("energy", observables.energy.into());
+            set("kinetic", observables.energy_breakdown.kinetic().into());
+            set("potential", observables.energy_breakdown.potential().into());
+            set("momentum", vector(observables.momentum));
+            set("angularMomentum", vector(observables.angular_momentum));
+            set("temperature", observables.temperature.into());
+        }
+        SimulationEvent::CloseApproach {
+            time,
+            first,
+            second,
+            distance,
+        } => {
+            set("time", (*time).into());
+            set("first", (*first as u32).into());
+            set("second", (*second as u32).into());
+            set("distance", (*distance).into());
+        }
+        SimulationEvent::DriftExceeded { drift } => {
+            set("time", drift.time.into());
+            set("energy", drift.energy.relative.into());
+            set("momentum", drift.momentum.relative.into());
+            set("angularMomentum", drift.angular_momentum.relative.into());
+        }
+        SimulationEvent::Reset => {}
+    }
+
+    object.into()
+}
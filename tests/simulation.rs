@@ -0,0 +1,914 @@
+use dipole_simulation::app::{
+    FixedStep, Follow, Gesture, Handle, Motion, Msg, Preset, Projection, Store, Tool, ARROW_SCALE,
+};
+use dipole_simulation::logging::{set_logger, NullLogger};
+use dipole_simulation::simulation::batch::{self, Length, RunConfig};
+use dipole_simulation::simulation::binary_trajectory::TrajectoryReader;
+use dipole_simulation::simulation::comparison::{Divergence, Run, Settings};
+use dipole_simulation::simulation::dipole::{DipoleSimulation, Edit, Model, Objects, Simulatable};
+use dipole_simulation::simulation::integrator::Integrator;
+use dipole_simulation::simulation::scenario::{ObjectSpec, Scenario};
+use dipole_simulation::simulation::sweep::{Parameter, Sweep};
+use dipole_simulation::simulation::trajectory::Trajectory;
+use nalgebra::{Point3, Vector3};
+use std::io::Cursor;
+
+#[test]
+fn dipoles_conserve_momentum() {
+    set_logger(Box::new(NullLogger));
+    let mut simulation = DipoleSimulation::new(1., 1., 1., 1.);
+    for _ in 0..200 {
+        simulation.update(0.01);
+    }
+
+    assert!(simulation.get_total_momentum().norm() < 1e-12);
+}
+
+#[test]
+fn energy_breaks_down_into_its_parts() {
+    set_logger(Box::new(NullLogger));
+    let sum = |simulation: &dyn Simulatable| {
+        let parts = simulation.get_energy_breakdown();
+        parts.translational
+            + parts.rotational
+            + parts.intra_dipole
+            + parts.inter_object
+            + parts.external
+    };
+
+    let mut dipoles = DipoleSimulation::new(1., 2., 1., 1.);
+    dipoles
+        .edit_object(0, Edit::AngularVelocity(Vector3::new(0., 0., 1.)))
+        .unwrap();
+    dipoles.update(0.01);
+    let parts = dipoles.get_energy_breakdown();
+    assert!(parts.translational > 0. && parts.rotational > 0.);
+    assert!(parts.intra_dipole != 0. && parts.inter_object != 0.);
+    assert_eq!(parts.external, 0.);
+    assert!((sum(&dipoles) - dipoles.get_total_energy()).abs() < 1e-12);
+
+    let mut charges = Scenario::from_json(include_str!("../scenarios/charge_orbit.json"))
+        .unwrap()
+        .build()
+        .unwrap();
+    let parts = charges.get_energy_breakdown();
+    assert_eq!(parts.rotational, 0.);
+    assert_eq!(parts.intra_dipole, 0.);
+    assert_eq!(parts.external, 0.);
+
+    // The charge of -5 at y = 10 sits 50 higher in a unit field along y
+    charges.set_external_field(Vector3::new(0., 1., 0.));
+    assert!((charges.get_energy_breakdown().external - 50.).abs() < 1e-12);
+    charges.set_external_field(Vector3::new(0., -1., 0.));
+    assert!((charges.get_energy_breakdown().external + 50.).abs() < 1e-12);
+    assert!((sum(charges.as_ref()) - charges.get_total_energy()).abs() < 1e-12);
+}
+
+#[test]
+fn binary_trajectory_round_trip() {
+    set_logger(Box::new(NullLogger));
+    let mut simulation = DipoleSimulation::new(1., 2., 1., 1.);
+    let mut trajectory = Trajectory::new(1);
+    for step in 0..10 {
+        simulation.update(0.01);
+        trajectory.record(&simulation, (step + 1) as f64 * 0.01);
+    }
+
+    let bytes = trajectory
+        .write_binary(Cursor::new(Vec::new()), "rk4")
+        .unwrap()
+        .into_inner();
+    let mut reader = TrajectoryReader::open(Cursor::new(bytes)).unwrap();
+
+    assert_eq!(reader.len(), 10);
+    assert_eq!(reader.header().integrator, "rk4");
+    assert_eq!(reader.frame_at_time(0.055), 4);
+    let frame = reader.read_frame(9).unwrap();
+    assert_eq!(
+        frame.objects[1].position,
+        trajectory.frames()[9].objects[1].position
+    );
+}
+
+#[test]
+fn text_exports_match_their_column_descriptions() {
+    set_logger(Box::new(NullLogger));
+    let mut simulation = DipoleSimulation::new(1., 2., 1., 1.);
+    let mut trajectory = Trajectory::new(1);
+    for step in 0..2 {
+        simulation.update(0.01);
+        trajectory.record(&simulation, (step + 1) as f64 * 0.01);
+    }
+    let frames = trajectory.frames();
+    let values = |fields: &[&str]| -> Vec<f64> {
+        fields.iter().map(|field| field.parse().unwrap()).collect()
+    };
+
+    let mut xyz = vec![];
+    trajectory.write_extended_xyz(&mut xyz).unwrap();
+    let xyz = String::from_utf8(xyz).unwrap();
+    let mut lines = xyz.lines();
+    for frame in frames {
+        assert_eq!(lines.next().unwrap(), "2");
+        let comment = lines.next().unwrap();
+        let properties = comment.split(' ').next().unwrap();
+        let properties = properties.strip_prefix("Properties=").unwrap();
+        let columns: Vec<_> = properties.split(':').collect();
+        let width: usize = columns
+            .chunks(3)
+            .map(|column| column[2].parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(width, 16);
+        assert!(comment.ends_with(&format!("Time={}", frame.time)));
+
+        for object in frame.objects.iter() {
+            let fields: Vec<_> = lines.next().unwrap().split(' ').collect();
+            assert_eq!(fields.len(), width);
+            assert_eq!(fields[0], "D");
+            let values = values(&fields[1..]);
+            assert_eq!(values[0..3], *object.position.as_slice());
+            assert_eq!(values[3..6], *object.orientation.as_slice());
+            assert_eq!(values[6..9], *object.velocity.as_slice());
+            assert_eq!(values[9..12], *object.angular_velocity.as_slice());
+            assert_eq!(values[12..], [object.mass, object.charge, object.offset]);
+        }
+    }
+    assert_eq!(lines.next(), None);
+
+    let mut csv = vec![];
+    trajectory.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    let width = lines.next().unwrap().split(',').count();
+    assert_eq!(width, 18);
+    for frame in frames {
+        for (index, object) in frame.objects.iter().enumerate() {
+            let fields: Vec<_> = lines.next().unwrap().split(',').collect();
+            assert_eq!(fields.len(), width);
+            assert_eq!(fields[0].parse::<f64>().unwrap(), frame.time);
+            assert_eq!(fields[1], index.to_string());
+            assert_eq!(fields[2], "dipole");
+            let values = values(&fields[3..]);
+            assert_eq!(values[0..3], *object.position.as_slice());
+            assert_eq!(values[12..], [object.mass, object.charge, object.offset]);
+        }
+    }
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn corrupt_binary_trajectories_are_rejected() {
+    set_logger(Box::new(NullLogger));
+    let mut simulation = DipoleSimulation::new(1., 2., 1., 1.);
+    let mut trajectory = Trajectory::new(1);
+    for step in 0..3 {
+        simulation.update(0.01);
+        trajectory.record(&simulation, (step + 1) as f64 * 0.01);
+    }
+    let bytes = trajectory
+        .write_binary(Cursor::new(Vec::new()), "rk4")
+        .unwrap()
+        .into_inner();
+    assert!(TrajectoryReader::open(Cursor::new(&bytes[..bytes.len() / 2])).is_err());
+
+    // Counts and lengths far beyond what the file holds
+    let header = |string_length: u32, object_count: u32, frame_count: u64| {
+        let mut bytes = b"DPLTRAJ\0".to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&string_length.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&object_count.to_le_bytes());
+        bytes.extend_from_slice(&frame_count.to_le_bytes());
+        bytes.extend_from_slice(&8u64.to_le_bytes());
+        bytes
+    };
+    assert!(TrajectoryReader::open(Cursor::new(header(0, 0, 0))).is_ok());
+    for bytes in [
+        header(u32::MAX, 0, 0),
+        header(0, u32::MAX, 0),
+        header(0, 0, u64::MAX),
+    ] {
+        assert!(TrajectoryReader::open(Cursor::new(bytes)).is_err());
+    }
+}
+
+#[test]
+fn store_advances_sim_time() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::UpdateSimulation(0.01));
+
+    assert!(store.state.sim_time() > 0.);
+}
+
+#[test]
+fn store_loads_scenarios_and_pauses() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Integrator(Integrator::Verlet));
+    store.msg(&Msg::UpdateSimulation(0.01));
+
+    let mut scenario = Scenario::two_dipoles();
+    scenario.objects.push(scenario.objects[0].clone());
+    scenario.objects[2].position = [0., 10., 0.];
+    store.msg(&Msg::LoadScenario(scenario));
+    assert_eq!(store.state.simulation().get_objects().len(), 3);
+    assert_eq!(store.state.sim_time(), 0.);
+
+    store.msg(&Msg::Paused(true));
+    store.msg(&Msg::UpdateSimulation(0.01));
+    assert_eq!(store.state.sim_time(), 0.);
+
+    store.msg(&Msg::Paused(false));
+    store.msg(&Msg::UpdateSimulation(0.01));
+    assert!(store.state.sim_time() > 0.);
+}
+
+#[test]
+fn store_raises_events() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    let mut scenario = Scenario::from_json(include_str!("../scenarios/charge_orbit.json")).unwrap();
+    scenario.objects[1].position = [0.5, 0., 0.];
+    scenario.objects[1].velocity = [0.; 3];
+    store.msg(&Msg::LoadScenario(scenario));
+    store.msg(&Msg::Integrator(Integrator::Euler));
+    store.msg(&Msg::DriftThreshold(1e-9));
+    store.msg(&Msg::CloseApproachDistance(2.));
+    for _ in 0..3 {
+        store.msg(&Msg::UpdateSimulation(0.01));
+    }
+    store.msg(&Msg::ResetSimulation);
+
+    let names: Vec<_> = store
+        .take_events()
+        .iter()
+        .map(|event| event.name())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "reset",
+            "step",
+            "closeapproach",
+            "driftexceeded",
+            "step",
+            "step",
+            "reset"
+        ]
+    );
+    assert!(store.take_events().is_empty());
+}
+
+#[test]
+fn changing_the_drift_threshold_flags_drift_afresh() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Integrator(Integrator::Euler));
+    store.msg(&Msg::DriftThreshold(1e-12));
+    store.msg(&Msg::UpdateSimulation(0.01));
+    let first = store.state.diagnostics().exceeded_at();
+    assert!(first.is_some());
+
+    store.msg(&Msg::DriftThreshold(1e-13));
+    assert!(!store.state.diagnostics().exceeded());
+    store.msg(&Msg::UpdateSimulation(0.01));
+    let second = store.state.diagnostics().exceeded_at();
+    assert!(second > first);
+    let names: Vec<_> = store
+        .take_events()
+        .iter()
+        .map(|event| event.name())
+        .filter(|name| *name == "driftexceeded")
+        .collect();
+    assert_eq!(names.len(), 2);
+}
+
+#[test]
+fn recordings_play_in_place_of_the_simulation_until_left() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::RecordTrajectory(true));
+    for _ in 0..5 {
+        store.msg(&Msg::UpdateSimulation(0.01));
+    }
+    let bytes = store
+        .state
+        .trajectory()
+        .write_binary(Cursor::new(Vec::new()), "rk4")
+        .unwrap()
+        .into_inner();
+
+    store.msg(&Msg::LoadTrajectory(bytes));
+    assert!(store.state.playing_back());
+    assert!(store.state.can_add(Objects::Dipole).is_err());
+
+    store.msg(&Msg::UpdateSimulation(0.01));
+    store.msg(&Msg::LeavePlayback);
+    assert!(!store.state.playing_back());
+    assert!(store.state.can_add(Objects::Dipole).is_ok());
+    assert_eq!(store.state.sim_time(), 0.);
+    assert_eq!(
+        store.state.simulation().get_objects()[1].get_pos(),
+        Vector3::new(10., 0., 0.)
+    );
+}
+
+#[test]
+fn scenario_round_trip() {
+    let scenario = Scenario::two_dipoles();
+    let parsed = Scenario::from_json(&scenario.to_json()).unwrap();
+    assert_eq!(parsed, scenario);
+
+    let simulation = parsed.build().unwrap();
+    assert_eq!(
+        Scenario::capture(simulation.as_ref(), &scenario.name),
+        scenario
+    );
+}
+
+#[test]
+fn batch_run_with_every_integrator() {
+    set_logger(Box::new(NullLogger));
+    for integrator in Integrator::ALL.iter() {
+        let mut simulation = Scenario::two_dipoles().build().unwrap();
+        let config = RunConfig {
+            integrator: *integrator,
+            dt: 0.01,
+            length: Length::Time(1.),
+            stride: 10,
+            drift_threshold: 0.01,
+            collapse_distance: None,
+        };
+        let mut samples = 0;
+        let summary = batch::run(simulation.as_mut(), &config, |_, _, _| {
+            samples += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(summary.steps, 100);
+        assert_eq!(samples, 11);
+        assert!(summary.max_drift.energy.relative < 1e-6);
+    }
+}
+
+#[test]
+fn run_time_is_split_into_whole_steps() {
+    let steps = |time, dt| {
+        RunConfig {
+            length: Length::Time(time),
+            dt,
+            ..RunConfig::default()
+        }
+        .steps()
+    };
+    // 1.1 / 0.1 comes out a hair over 11
+    assert_eq!(steps(1.1, 0.1), 11);
+    assert_eq!(steps(1.15, 0.1), 12);
+    assert_eq!(steps(0., 0.1), 0);
+}
+
+#[test]
+fn sweep_expands_the_grid_in_order() {
+    set_logger(Box::new(NullLogger));
+    let sweep = Sweep {
+        scenario: Scenario::two_dipoles(),
+        axes: vec![
+            "offset=0.05:0.2:4".parse().unwrap(),
+            "mass[1]=1,2".parse().unwrap(),
+        ],
+        config: RunConfig {
+            length: Length::Steps(10),
+            ..RunConfig::default()
+        },
+        jitter: 0.,
+    };
+
+    let results = sweep.run(3);
+    assert_eq!(results.len(), 8);
+    for (index, result) in results.iter().enumerate() {
+        assert_eq!(result.case.index, index);
+        assert!(result.outcome.is_ok());
+    }
+    let (parameter, offset) = results[5].case.values[0];
+    assert_eq!(parameter, Parameter::Offset(None));
+    assert!((offset - 0.15).abs() < 1e-12);
+    assert_eq!(results[5].case.values[1], (Parameter::Mass(Some(1)), 2.));
+}
+
+#[test]
+fn seeds_without_jitter_repeat_the_scenario() {
+    set_logger(Box::new(NullLogger));
+    let sweep = Sweep {
+        scenario: Scenario::two_dipoles(),
+        axes: vec!["seed=0:2:3".parse().unwrap()],
+        config: RunConfig {
+            length: Length::Steps(10),
+            ..RunConfig::default()
+        },
+        jitter: 0.,
+    };
+
+    let results = sweep.run(2);
+    assert_eq!(results.len(), 3);
+    for result in results.iter() {
+        let scenario = sweep.scenario_for(&result.case).unwrap();
+        assert_eq!(scenario.objects, sweep.scenario.objects);
+        assert!(result.outcome.is_ok());
+    }
+}
+
+#[test]
+fn comparison_runs_in_lock_step() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Compare(Some(Settings::default())));
+    for _ in 0..100 {
+        store.msg(&Msg::UpdateSimulation(0.01));
+    }
+    assert_eq!(store.state.divergence(), Some(Divergence::default()));
+
+    let settings = Settings::from_json(r#"{"integrator": "euler", "substeps": 2}"#).unwrap();
+    assert_eq!(settings.integrator, Integrator::Euler);
+    assert_eq!(settings.offset, None);
+    store.msg(&Msg::Compare(Some(settings)));
+    assert_eq!(store.state.sim_time(), 0.);
+    for _ in 0..100 {
+        store.msg(&Msg::UpdateSimulation(0.01));
+    }
+    let divergence = store.state.divergence().unwrap();
+    assert!(divergence.orientation > 0.);
+    assert!(divergence.max_position >= divergence.position);
+
+    store.msg(&Msg::Compare(None));
+    assert!(store.state.divergence().is_none());
+}
+
+#[test]
+fn point_dipoles_match_finite_ones_from_afar() {
+    set_logger(Box::new(NullLogger));
+    let mut finite = DipoleSimulation::new(1., 2., 1., 1.);
+    let mut point = DipoleSimulation::new(1., 2., 1., 1.);
+    point.set_model(Model::Point);
+    for simulation in [&mut finite, &mut point] {
+        simulation
+            .edit_object(1, Edit::Orientation(Vector3::new(1., 1., 0.)))
+            .unwrap();
+    }
+
+    // Ten units apart, the charges of each dipole are close enough to look like one point
+    for ((force, torque), (point_force, point_torque)) in
+        finite.get_forces().iter().zip(point.get_forces().iter())
+    {
+        assert!(force.norm() > 0. && torque.norm() > 0.);
+        assert!((force - point_force).norm() < 1e-3 * force.norm());
+        assert!((torque - point_torque).norm() < 1e-3 * torque.norm());
+    }
+    let inter = |simulation: &DipoleSimulation| simulation.get_energy_breakdown().inter_object;
+    assert!((inter(&finite) - inter(&point)).abs() < 1e-3 * inter(&finite).abs());
+    let field = |simulation: &DipoleSimulation| simulation.get_field(Vector3::new(5., 3., 0.));
+    assert!((field(&finite) - field(&point)).norm() < 1e-2 * field(&finite).norm());
+
+    // Point dipoles still conserve what they should
+    let energy = point.get_total_energy();
+    for _ in 0..200 {
+        point.update(0.01);
+    }
+    assert!(point.get_total_momentum().norm() < 1e-12);
+    assert!(point.get_total_angular_momentum().norm() < 1e-9);
+    assert!((point.get_total_energy() - energy).abs() < 1e-6 * energy.abs());
+
+    let settings = Settings::from_json(r#"{"model": "point"}"#).unwrap();
+    assert_eq!(settings.model, Model::Point);
+    let run = Run::new(&Scenario::two_dipoles(), settings).unwrap();
+    let mut expected = Scenario::two_dipoles().build().unwrap();
+    expected.set_model(Model::Point);
+    assert_eq!(run.simulation().get_forces(), expected.get_forces());
+}
+
+#[test]
+fn fixed_steps_carry_the_remainder_and_cap_long_frames() {
+    let mut stepper = FixedStep::new(0.01, 10);
+    assert_eq!(stepper.advance(0.025), 2);
+    assert!((stepper.alpha() - 0.5).abs() < 1e-9);
+    assert_eq!(stepper.advance(0.005), 1);
+    assert_eq!(stepper.advance(5.), 10);
+    assert_eq!(stepper.alpha(), 0.);
+
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Paused(true));
+    store.msg(&Msg::UpdateSimulation(1.));
+    assert_eq!(store.state.sim_time(), 0.);
+    store.msg(&Msg::Step);
+    assert_eq!(store.state.sim_time(), store.state.physics_dt());
+}
+
+#[test]
+fn clicking_selects_the_object_under_the_mouse() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    let mut click = |x, y| {
+        store.msg(&Msg::MouseDown(x, y));
+        store.msg(&Msg::MouseUp);
+        store.state.selected()
+    };
+
+    // The camera looks down the z axis at the dipole at the origin, with the other at x = 10
+    assert_eq!(click(256, 256), Some(0));
+    assert_eq!(click(404, 256), Some(1));
+    assert_eq!(click(100, 100), None);
+
+    // Dragging orbits the camera instead of selecting
+    store.msg(&Msg::Select(Some(1)));
+    store.msg(&Msg::MouseDown(256, 256));
+    store.msg(&Msg::MouseMove(300, 256));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.selected(), Some(1));
+
+    store.msg(&Msg::Select(Some(5)));
+    assert_eq!(store.state.selected(), None);
+}
+
+#[test]
+fn objects_can_be_edited_one_at_a_time() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    let forces = store.state.simulation().get_forces();
+    assert!((forces[0].0 + forces[1].0).norm() < 1e-12);
+
+    store.msg(&Msg::EditObject(1, Edit::Mass(3.)));
+    store.msg(&Msg::EditObject(
+        1,
+        Edit::Position(Vector3::new(0., 5., 0.)),
+    ));
+    store.msg(&Msg::EditObject(
+        1,
+        Edit::Orientation(Vector3::new(0., 0., 2.)),
+    ));
+    store.msg(&Msg::EditObject(1, Edit::Offset(-1.)));
+    store.msg(&Msg::EditObject(7, Edit::Mass(1.)));
+    let objects = store.state.simulation().get_objects();
+    assert_eq!(objects[0].get_mass(), 1.);
+    assert_eq!(objects[1].get_mass(), 3.);
+    assert_eq!(objects[1].get_pos(), Vector3::new(0., 5., 0.));
+    assert_eq!(objects[1].get_orientation(), Vector3::new(0., 0., 1.));
+    assert_eq!(objects[1].get_offset(), 0.1);
+
+    // The mass stays after a reset but the object goes back to where it started
+    store.msg(&Msg::ResetSimulation);
+    let objects = store.state.simulation().get_objects();
+    assert_eq!(objects[1].get_mass(), 3.);
+    assert_eq!(objects[1].get_pos(), Vector3::new(10., 0., 0.));
+}
+
+#[test]
+fn objects_can_be_added_duplicated_and_deleted() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    let count = |store: &Store| store.state.simulation().get_objects().len();
+
+    // Clicking with the add tool puts a dipole where the click meets the plane of the origin
+    store.msg(&Msg::Tool(Tool::Add(Objects::Dipole)));
+    store.msg(&Msg::MouseDown(404, 256));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(count(&store), 3);
+    assert_eq!(store.state.selected(), Some(2));
+    assert_eq!(store.state.tool(), Tool::Select);
+    let position = store.state.simulation().get_objects()[2].get_pos();
+    assert!((position - Vector3::new(10., 0., 0.)).norm() < 0.1);
+
+    // Editing carries on from where the simulation got to
+    store.msg(&Msg::UpdateSimulation(0.01));
+    let time = store.state.sim_time();
+    store.msg(&Msg::DuplicateObject(0));
+    assert_eq!(count(&store), 4);
+    assert_eq!(store.state.sim_time(), time);
+    assert!(store.state.history().samples().count() > 0);
+    assert_eq!(store.state.selected(), Some(3));
+    let position = store.state.simulation().get_objects()[3].get_pos();
+    assert!((position - Vector3::new(1.5, 0., 0.)).norm() < 1e-6);
+
+    // Dipoles and point charges can't share a simulation, so adding a charge is refused with
+    // the reason, whether by message or by picking the tool
+    assert!(store.state.can_add(Objects::Dipole).is_ok());
+    assert!(store.state.can_add(Objects::Charge).is_err());
+    store.msg(&Msg::AddObject(ObjectSpec::new(
+        Objects::Charge,
+        [0., 5., 0.],
+    )));
+    assert_eq!(count(&store), 4);
+    assert!(store.state.add_error().is_some());
+    store.msg(&Msg::Tool(Tool::Add(Objects::Charge)));
+    assert_eq!(store.state.tool(), Tool::Select);
+    store.msg(&Msg::DuplicateObject(0));
+    assert_eq!(count(&store), 5);
+    assert_eq!(store.state.add_error(), None);
+    store.msg(&Msg::DeleteObject(4));
+
+    store.msg(&Msg::Select(Some(3)));
+    store.msg(&Msg::DeleteObject(1));
+    assert_eq!(count(&store), 3);
+    assert_eq!(store.state.selected(), Some(2));
+    store.msg(&Msg::ResetSimulation);
+    assert_eq!(count(&store), 3);
+
+    // Once empty, either kind can be added
+    for _ in 0..3 {
+        store.msg(&Msg::DeleteObject(0));
+    }
+    assert_eq!(count(&store), 0);
+    assert!(store.state.can_add(Objects::Charge).is_ok());
+    store.msg(&Msg::AddObject(ObjectSpec::new(
+        Objects::Charge,
+        [0., 5., 0.],
+    )));
+    assert_eq!(count(&store), 1);
+    assert_eq!(store.state.selected(), Some(0));
+}
+
+#[test]
+fn gizmo_drags_and_turns_the_selected_object() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Select(Some(1)));
+
+    // The dipole at x = 10 is drawn at pixel 404, 15 pixels per unit. Its x handle runs right.
+    store.msg(&Msg::MouseDown(426, 256));
+    assert_eq!(store.state.dragging(), Some(Handle::Axis(0)));
+    assert!(store.state.paused());
+    store.msg(&Msg::MouseMove(456, 256));
+    store.msg(&Msg::MouseUp);
+    assert!(!store.state.paused());
+    assert_eq!(store.state.selected(), Some(1));
+    let position = store.state.simulation().get_objects()[1].get_pos();
+    assert!((position - Vector3::new(12., 0., 0.)).norm() < 0.1);
+
+    // Dragging the ring about z a quarter turn anticlockwise turns the dipole from y to -x
+    store.msg(&Msg::EditObject(
+        1,
+        Edit::Position(Vector3::new(10., 0., 0.)),
+    ));
+    store.msg(&Msg::MouseDown(424, 236));
+    assert_eq!(store.state.dragging(), Some(Handle::Ring(2)));
+    store.msg(&Msg::MouseMove(384, 236));
+    store.msg(&Msg::MouseUp);
+    let orientation = store.state.simulation().get_objects()[1].get_orientation();
+    assert!((orientation - Vector3::new(-1., 0., 0.)).norm() < 0.05);
+}
+
+#[test]
+fn slingshot_sets_the_velocity_objects_reset_to() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Tool(Tool::Slingshot(Motion::Linear)));
+    assert_eq!(store.state.arrows().len(), 2);
+    assert!(store.state.gizmo().is_none());
+
+    // Pulling an arrow two units right out of the dipole at x = 10
+    let (x, y) = store
+        .state
+        .canvas_position(Vector3::new(12., 0., 0.))
+        .unwrap();
+    store.msg(&Msg::MouseDown(404, 256));
+    store.msg(&Msg::MouseMove(x.round() as i32, y.round() as i32));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.selected(), Some(1));
+    let velocity = store.state.simulation().get_objects()[1].get_velocity();
+    let expected = Vector3::new(2. / ARROW_SCALE, 0., 0.);
+    assert!((velocity - expected).norm() < 0.01);
+    let arrow = store.state.arrows()[1];
+    assert!((arrow.end() - Vector3::new(12., 0., 0.)).norm() < 0.05);
+
+    for _ in 0..50 {
+        store.msg(&Msg::UpdateSimulation(0.02));
+    }
+    store.msg(&Msg::ResetSimulation);
+    let object = store.state.simulation().get_objects()[1];
+    assert_eq!(object.get_pos(), Vector3::new(10., 0., 0.));
+    assert_eq!(object.get_velocity(), velocity);
+
+    // Point charges don't turn
+    store.msg(&Msg::LoadScenario(Scenario {
+        objects: vec![ObjectSpec::new(Objects::Charge, [0., 0., 0.])],
+        ..Scenario::two_dipoles()
+    }));
+    store.msg(&Msg::Tool(Tool::Slingshot(Motion::Angular)));
+    assert!(store.state.arrows().is_empty());
+}
+
+#[test]
+fn camera_pans_and_follows_the_objects() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    let target = |store: &Store| store.state.camera().target().coords.cast::<f64>();
+
+    // Panning drags the scene along with the mouse, without selecting anything
+    store.msg(&Msg::PanStart(256, 256));
+    store.msg(&Msg::MouseMove(306, 256));
+    store.msg(&Msg::MouseUp);
+    assert!(target(&store).x < -1.);
+    assert_eq!(store.state.selected(), None);
+    let (x, _) = store.state.canvas_position(Vector3::zeros()).unwrap();
+    assert!((x - 306.).abs() < 1.);
+
+    // Looking at the second dipole puts it under the middle of the canvas
+    store.msg(&Msg::CameraTarget(Vector3::new(10., 0., 0.)));
+    store.msg(&Msg::MouseDown(256, 256));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.selected(), Some(1));
+
+    // The dipoles pull together, and the camera stays on the one selected
+    store.msg(&Msg::Follow(Follow::Selected));
+    for _ in 0..100 {
+        store.msg(&Msg::UpdateSimulation(0.02));
+    }
+    let (x, y) = store
+        .state
+        .canvas_position(store.state.simulation().get_objects()[1].get_pos())
+        .unwrap();
+    assert!(store.state.simulation().get_objects()[1].get_pos().x < 10.);
+    assert!((x - 256.).abs() < 1. && (y - 256.).abs() < 1.);
+
+    // Equal masses keep their centre of mass half way between them
+    store.msg(&Msg::Follow(Follow::CenterOfMass));
+    assert!((target(&store) - Vector3::new(5., 0., 0.)).norm() < 1e-3);
+
+    // Moving the view by hand stops it following
+    store.msg(&Msg::PanStart(256, 256));
+    store.msg(&Msg::MouseMove(256, 300));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.follow(), Follow::Free);
+    assert!(target(&store).y > 1.);
+}
+
+#[test]
+fn framing_fits_large_scenarios_in_the_view() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::LoadScenario(Scenario {
+        objects: vec![
+            ObjectSpec::new(Objects::Charge, [-200., 0., 0.]),
+            ObjectSpec::new(Objects::Charge, [200., 50., 0.]),
+        ],
+        ..Scenario::two_dipoles()
+    }));
+    let bounds = store.state.scene_bounds().unwrap();
+    assert!((bounds.center - Vector3::new(0., 25., 0.)).norm() < 1e-9);
+
+    // Zooming out is no longer held to a close view of the origin
+    store.msg(&Msg::Zoom(1000.));
+    let radius = store.state.camera().orbit_radius();
+    assert!(radius > 200. && radius < 3000.);
+
+    store.msg(&Msg::FrameAll);
+    let (near, far) = store.state.camera().clip_planes();
+    for object in store.state.simulation().get_objects() {
+        let (x, y) = store.state.canvas_position(object.get_pos()).unwrap();
+        assert!((0. ..512.).contains(&x) && (0. ..512.).contains(&y));
+        let distance = (object.get_pos() - store.state.camera().get_eye_pos().coords.cast()).norm();
+        assert!(near < distance as f32 && (distance as f32) < far);
+    }
+
+    // Framing one object brings it up close, in the middle of the view
+    store.msg(&Msg::Select(Some(1)));
+    store.msg(&Msg::FrameSelection);
+    assert!(store.state.camera().orbit_radius() < 5.);
+    let (x, y) = store
+        .state
+        .canvas_position(Vector3::new(200., 50., 0.))
+        .unwrap();
+    assert!((x - 256.).abs() < 1. && (y - 256.).abs() < 1.);
+
+    // A smaller scene pulls the eye back in reach of its zoom
+    store.msg(&Msg::Zoom(1000.));
+    store.msg(&Msg::LoadScenario(Scenario::two_dipoles()));
+    let radius = store.state.camera().orbit_radius();
+    store.msg(&Msg::Zoom(0.));
+    assert_eq!(store.state.camera().orbit_radius(), radius);
+    assert!(radius < 200.);
+}
+
+#[test]
+fn preset_views_swing_round_and_flat_views_ignore_depth() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    store.msg(&Msg::Paused(true));
+
+    // The camera swings round over a few frames rather than jumping
+    store.msg(&Msg::LookFrom(Preset::Top));
+    store.msg(&Msg::UpdateSimulation(0.1));
+    assert!(store.state.camera().animating());
+    assert!(store.state.camera().forward().y > -0.99);
+    for _ in 0..10 {
+        store.msg(&Msg::UpdateSimulation(0.1));
+    }
+    assert!(!store.state.camera().animating());
+    assert!(store.state.camera().forward().y < -0.999);
+
+    // Orbiting by hand takes over from a swing under way
+    store.msg(&Msg::LookFrom(Preset::Front));
+    store.msg(&Msg::UpdateSimulation(0.1));
+    store.msg(&Msg::MouseDown(10, 10));
+    store.msg(&Msg::MouseMove(60, 10));
+    store.msg(&Msg::MouseUp);
+    assert!(!store.state.camera().animating());
+
+    store.msg(&Msg::LookFrom(Preset::Front));
+    store.msg(&Msg::UpdateSimulation(1.));
+    let depth = |store: &Store| {
+        let near = store.state.canvas_position(Vector3::new(10., 0., 10.));
+        let far = store.state.canvas_position(Vector3::new(10., 0., 0.));
+        near.unwrap().0 - far.unwrap().0
+    };
+    assert!(depth(&store) > 10.);
+
+    // Flat views draw things the same size however near they are, and can still be clicked
+    store.msg(&Msg::Projection(Projection::Orthographic));
+    assert!(depth(&store).abs() < 1e-3);
+    let (x, y) = store
+        .state
+        .canvas_position(Vector3::new(10., 0., 0.))
+        .unwrap();
+    assert!((x - 404.).abs() < 2.);
+    store.msg(&Msg::MouseDown(x as i32, y as i32));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.selected(), Some(1));
+}
+
+#[test]
+fn resized_canvases_keep_things_their_shape() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+    let square = store
+        .state
+        .canvas_position(Vector3::new(10., 0., 0.))
+        .unwrap();
+
+    // Twice as wide shows more to either side at the same scale
+    store.msg(&Msg::CanvasSize(1024, 512));
+    let (x, y) = store
+        .state
+        .canvas_position(Vector3::new(10., 0., 0.))
+        .unwrap();
+    assert!((x - 512. - (square.0 - 256.)).abs() < 0.01 && (y - square.1).abs() < 0.01);
+    let (_, top) = store
+        .state
+        .canvas_position(Vector3::new(0., 10., 0.))
+        .unwrap();
+    assert!((256. - top - (x - 512.)).abs() < 0.01);
+    store.msg(&Msg::MouseDown(x as i32, y as i32));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.selected(), Some(1));
+
+    // Each half of a split view has the shape of the whole canvas
+    store.msg(&Msg::Compare(Some(Settings::default())));
+    let (x, y) = store
+        .state
+        .canvas_position(Vector3::new(10., 0., 0.))
+        .unwrap();
+    assert!((x - 256. - (square.0 - 256.) / 2.).abs() < 0.01 && (y - 256.).abs() < 0.01);
+    store.msg(&Msg::Select(None));
+    store.msg(&Msg::MouseDown(x as i32, y as i32));
+    store.msg(&Msg::MouseUp);
+    assert_eq!(store.state.selected(), Some(1));
+}
+
+#[test]
+fn fingers_tap_pinch_pan_and_twist() {
+    set_logger(Box::new(NullLogger));
+    let mut store = Store::new();
+
+    // A tap selects like a click
+    store.msg(&Msg::TouchStart(vec![(404, 256)]));
+    store.msg(&Msg::TouchEnd(vec![]));
+    assert_eq!(store.state.selected(), Some(1));
+    assert_eq!(store.state.gesture(), None);
+
+    // Spreading two fingers to twice as far apart halves the distance to the scene
+    let radius = store.state.camera().orbit_radius();
+    store.msg(&Msg::TouchStart(vec![(200, 256)]));
+    store.msg(&Msg::TouchStart(vec![(200, 256), (300, 256)]));
+    store.msg(&Msg::TouchMove(vec![(150, 256), (350, 256)]));
+    assert!((store.state.camera().orbit_radius() - radius / 2.).abs() < 1e-3);
+    assert_eq!(store.state.camera().target(), Point3::origin());
+
+    // Moving them together pans, and twisting them orbits
+    let forward = store.state.camera().forward();
+    store.msg(&Msg::TouchMove(vec![(150, 306), (350, 306)]));
+    assert!(store.state.camera().target().y > 0.1);
+    assert_eq!(store.state.camera().forward(), forward);
+    store.msg(&Msg::TouchMove(vec![(250, 206), (250, 406)]));
+    assert!((store.state.camera().forward() - forward).norm() > 0.1);
+
+    // The finger left after a pinch neither orbits nor taps
+    store.msg(&Msg::TouchEnd(vec![(250, 206)]));
+    assert_eq!(store.state.gesture(), Some(Gesture::Finished));
+    let forward = store.state.camera().forward();
+    store.msg(&Msg::TouchMove(vec![(100, 100)]));
+    store.msg(&Msg::TouchEnd(vec![]));
+    assert_eq!(store.state.camera().forward(), forward);
+    assert_eq!(store.state.selected(), Some(1));
+    assert_eq!(store.state.gesture(), None);
+}
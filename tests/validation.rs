@@ -0,0 +1,44 @@
+use dipole_simulation::logging::{set_logger, NullLogger};
+use dipole_simulation::simulation::integrator::Integrator;
+use dipole_simulation::simulation::validation::{validate_all, Reference};
+
+fn tolerance(integrator: Integrator) -> f64 {
+    match integrator {
+        Integrator::Euler => 1e-4,
+        Integrator::Verlet => 1e-5,
+        Integrator::Rk4 => 1e-8,
+    }
+}
+
+#[test]
+fn integrators_match_analytic_results() {
+    set_logger(Box::new(NullLogger));
+    for integrator in Integrator::ALL.iter() {
+        for validation in validate_all(*integrator, 0.001) {
+            println!("{}", validation);
+            assert!(
+                validation.relative_error() < tolerance(*integrator),
+                "{}",
+                validation
+            );
+        }
+    }
+}
+
+#[test]
+fn errors_shrink_at_the_order_of_the_integrator() {
+    set_logger(Box::new(NullLogger));
+    let reference = Reference::Libration { amplitude: 0.1 };
+    for (integrator, order) in [(Integrator::Verlet, 2), (Integrator::Rk4, 4)].iter() {
+        let coarse = reference.validate(*integrator, 0.01).relative_error();
+        let fine = reference.validate(*integrator, 0.005).relative_error();
+        // Halving the step divides the error by 2^order, give or take
+        assert!(
+            coarse / fine > 2f64.powi(*order) * 0.75,
+            "{} went from {:e} to {:e}",
+            integrator,
+            coarse,
+            fine
+        );
+    }
+}